@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+use serenity::model::id::UserId;
+
+/// Length of the steps windows are found in, in minutes.
+const SLOT_MINUTES: i64 = 30;
+/// Shortest window worth suggesting, in slots.
+const MIN_SLOTS: usize = 2;
+
+/// A start and end in UTC.
+type Span = (DateTime<Utc>, DateTime<Utc>);
+
+/// A weekly stretch of time someone can play, in their own time zone.
+/// Stretches ending at or before their start run past midnight.
+#[derive(Clone,Debug,PartialEq,Deserialize,Serialize)]
+pub struct Availability {
+    pub day: Weekday,
+    pub from: NaiveTime,
+    pub until: NaiveTime,
+}
+impl Availability {
+    /// Where the stretch falls in the week starting at `from`, in UTC. Both
+    /// this week's and last week's are given, as either may overlap it.
+    fn occurrences(&self, zone: Tz, from: DateTime<Utc>) -> Vec<Span> {
+        let local = from.with_timezone(&zone).naive_local().date();
+        let ahead = (7 + self.day.num_days_from_monday() as i64
+            - local.weekday().num_days_from_monday() as i64) % 7;
+        let length = match self.until.signed_duration_since(self.from) {
+            d if d > Duration::zero() => d,
+            d => d + Duration::days(1),
+        };
+        [ahead - 7, ahead].iter()
+            .map(|days| (local + Duration::days(*days)).and_time(self.from))
+            .filter_map(|start| {
+                let utc = zone.from_local_datetime(&start).earliest()?.with_timezone(&Utc);
+                Some((utc, utc + length))
+            })
+            .collect()
+    }
+}
+/// Parses `fri 19:00-23:00`, with the day and times separated by a space.
+impl FromStr for Availability {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || format!{"Invalid availability '{}', ex: fri 19:00-23:00", s};
+        let (day, times) = s.trim().split_once(' ').ok_or_else(usage)?;
+        let (from, until) = times.trim().split_once('-').ok_or_else(usage)?;
+        Ok(Availability {
+            day: day.parse::<Weekday>().map_err(|_| usage())?,
+            from: NaiveTime::parse_from_str(from.trim(), "%H:%M").map_err(|_| usage())?,
+            until: NaiveTime::parse_from_str(until.trim(), "%H:%M").map_err(|_| usage())?,
+        })
+    }
+}
+impl fmt::Display for Availability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!{f, "{:?} {}-{}", self.day, self.from.format("%H:%M"), self.until.format("%H:%M")}
+    }
+}
+
+/// A stretch of time in the coming week and who can play all through it.
+#[derive(Debug,PartialEq)]
+pub struct Window {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub available: BTreeSet<UserId>,
+}
+
+/// Someone's time zone and the weekly times they can play.
+pub struct Member<'a> {
+    pub user: UserId,
+    pub zone: Tz,
+    pub availability: &'a [Availability],
+}
+
+/// The windows in the week after `now` when the most members can play,
+/// longest first among those with as many members, at most `limit`.
+pub fn best_windows(members: &[Member<'_>], now: DateTime<Utc>, limit: usize) -> Vec<Window> {
+    // start on the next slot boundary
+    let past = (now.minute() as i64 % SLOT_MINUTES) * 60 + now.second() as i64;
+    let first = now + Duration::seconds(if past == 0 { 0 } else { SLOT_MINUTES * 60 - past })
+        - Duration::nanoseconds(now.nanosecond() as i64);
+    let slot = Duration::minutes(SLOT_MINUTES);
+    let slots = (7 * 24 * 60 / SLOT_MINUTES) as i32;
+    let stretches: Vec<(UserId, Vec<Span>)> = members.iter()
+        .map(|m| (m.user, m.availability.iter().flat_map(|a| a.occurrences(m.zone, first)).collect()))
+        .collect();
+    let mut runs: Vec<Window> = Vec::new();
+    for i in 0..slots {
+        let start = first + slot * i;
+        let available: BTreeSet<UserId> = stretches.iter()
+            .filter(|(_, times)| times.iter().any(|(from, until)| *from <= start && start + slot <= *until))
+            .map(|(user, _)| *user)
+            .collect();
+        match runs.last_mut() {
+            Some(run) if run.end == start && run.available == available => run.end = start + slot,
+            _ => runs.push(Window { start, end: start + slot, available }),
+        };
+    }
+    let mut windows: Vec<Window> = runs.into_iter()
+        .filter(|w| !w.available.is_empty() && w.end - w.start >= slot * MIN_SLOTS as i32)
+        .collect();
+    windows.sort_by(|a, b| b.available.len().cmp(&a.available.len())
+        .then((b.end - b.start).cmp(&(a.end - a.start)))
+        .then(a.start.cmp(&b.start)));
+    windows.truncate(limit);
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{America, Europe};
+
+    fn available(s: &str) -> Availability {
+        s.parse().unwrap()
+    }
+
+    // a wednesday
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2024, 5, 15).and_hms(12, 10, 0)
+    }
+
+    #[test]
+    fn parses_weekly_times() {
+        let friday = available("Fri 19:00-23:30");
+        assert_eq!(friday.day, Weekday::Fri);
+        assert_eq!(friday.to_string(), "Fri 19:00-23:30");
+        assert_eq!(available("saturday 22:00-02:00").until, NaiveTime::from_hms(2, 0, 0));
+        assert!("fri".parse::<Availability>().is_err());
+        assert!("fri 7pm-11pm".parse::<Availability>().is_err());
+        assert!("someday 19:00-23:00".parse::<Availability>().is_err());
+    }
+
+    #[test]
+    fn finds_when_most_can_play_across_zones() {
+        // 19:00-23:00 in Berlin (UTC+2) and 14:00-18:00 in New York (UTC-4)
+        // overlap from 18:00 to 21:00 UTC on friday and saturday
+        let berlin = [available("fri 19:00-23:00"), available("sat 19:00-23:00")];
+        let new_york = [available("fri 14:00-18:00"), available("sat 16:00-18:00")];
+        let members = [
+            Member { user: UserId(1), zone: Europe::Berlin, availability: &berlin },
+            Member { user: UserId(2), zone: America::New_York, availability: &new_york },
+        ];
+        let windows = best_windows(&members, now(), 2);
+        let both: BTreeSet<UserId> = [UserId(1), UserId(2)].into_iter().collect();
+        assert_eq!(windows, vec![
+            Window { start: Utc.ymd(2024, 5, 17).and_hms(18, 0, 0), end: Utc.ymd(2024, 5, 17).and_hms(21, 0, 0), available: both.clone() },
+            Window { start: Utc.ymd(2024, 5, 18).and_hms(20, 0, 0), end: Utc.ymd(2024, 5, 18).and_hms(21, 0, 0), available: both },
+        ]);
+    }
+
+    #[test]
+    fn includes_stretches_past_midnight_and_from_last_week() {
+        // wednesday 12:10 UTC is still tuesday night's stretch in Los Angeles
+        let late = [available("tue 22:00-07:00")];
+        let members = [Member { user: UserId(1), zone: America::Los_Angeles, availability: &late }];
+        let windows = best_windows(&members, now(), 5);
+        assert_eq!(windows[0].start, Utc.ymd(2024, 5, 22).and_hms(5, 0, 0));
+        // the week looked at ends on the slot it starts on
+        assert_eq!(windows[0].end, Utc.ymd(2024, 5, 22).and_hms(12, 30, 0));
+        assert_eq!(windows[1].start, Utc.ymd(2024, 5, 15).and_hms(12, 30, 0));
+        assert_eq!(windows[1].end, Utc.ymd(2024, 5, 15).and_hms(14, 0, 0));
+    }
+}
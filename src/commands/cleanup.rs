@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+use serenity::prelude::*;
+use serenity::client::Context;
+use serenity::http::StatusCode;
+use serenity::model::id::{GuildId, UserId};
+
+use game_randomizer::suggestions::SuggestionState;
+
+use crate::commands::suggestions::GameSuggestions;
+use crate::commands::players::PlayerContainer;
+use crate::commands::settings::{guild_settings, DepartedPolicy, GuildSettingsContainer};
+use crate::commands::gamenights::GameNightContainer;
+
+/// Days a guild's data is kept after the bot is removed, in case it is
+/// invited back.
+pub const GRACE_PERIOD_DAYS: i64 = 14;
+
+/// Guilds the bot was removed from and when that happened.
+pub struct DepartedGuilds;
+impl TypeMapKey for DepartedGuilds {
+    type Value = HashMap<GuildId, DateTime<Utc>>;
+}
+
+/// Whether the user is in any guild with the bot other than `gid`. Guilds
+/// that can't be asked count as shared, so profiles are only dropped when
+/// discord says the user is in none of them.
+async fn shares_guild(ctx: &Context, user: UserId, gid: GuildId) -> bool {
+    for other in ctx.cache.guilds().await.into_iter().filter(|g| *g != gid) {
+        if ctx.cache.member(other, user).await.is_some() {
+            return true;
+        }
+        match ctx.http.get_member(other.0, user.0).await {
+            Ok(_) => return true,
+            Err(SerenityError::Http(e)) if e.status_code() == Some(StatusCode::NOT_FOUND) => {},
+            Err(why) => {
+                warn!("Could not check whether {} is in guild {}: {:?}", user, other, why);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Applies the guild's policy to a departed member's suggestions and hides
+/// their profile there, dropping it when they share no other guild with
+/// the bot.
+pub async fn member_left(ctx: &Context, gid: GuildId, user: UserId) {
+    let policy = guild_settings(ctx, Some(gid)).await.departed;
+    let bot = ctx.cache.current_user_id().await;
+    let elsewhere = shares_guild(ctx, user, gid).await;
+    let mut wlock = ctx.data.write().await;
+    wlock.get_mut::<PlayerContainer>().expect("no players write data").member_left(user, gid, elsewhere);
+    let books = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+    let book = match books.get_mut(&gid) {
+        Some(b) => b,
+        None => return
+    };
+    for suggestion in book.iter_mut().filter(|s| s.user == user) {
+        match policy {
+            DepartedPolicy::Keep => {},
+            DepartedPolicy::Archive if suggestion.state.is_active() => {
+                let _ = suggestion.transition(SuggestionState::Dropped, bot);
+            },
+            DepartedPolicy::Archive => {},
+            DepartedPolicy::Transfer(to) => suggestion.user = to,
+        };
+    }
+    info!("Cleaned up after {} left guild {}", user, gid);
+}
+
+/// Shows the profile of a member who came back.
+pub async fn member_joined(ctx: &Context, gid: GuildId, user: UserId) {
+    let mut wlock = ctx.data.write().await;
+    wlock.get_mut::<PlayerContainer>().expect("no players write data").member_joined(user, gid);
+}
+
+/// Remembers that the bot was removed from a guild, starting its grace period.
+pub async fn guild_left(ctx: &Context, gid: GuildId) {
+    let mut wlock = ctx.data.write().await;
+    let departed = wlock.get_mut::<DepartedGuilds>().expect("no departed guilds write data");
+    departed.entry(gid).or_insert_with(Utc::now);
+    info!("Removed from guild {}, keeping its data for {} days", gid, GRACE_PERIOD_DAYS);
+}
+
+/// Keeps a guild's data again when the bot is back in it.
+pub async fn guild_joined(ctx: &Context, gid: GuildId) {
+    let mut wlock = ctx.data.write().await;
+    let departed = wlock.get_mut::<DepartedGuilds>().expect("no departed guilds write data");
+    if departed.remove(&gid).is_some() {
+        info!("Rejoined guild {} within its grace period", gid);
+    }
+}
+
+/// Deletes everything stored for guilds whose grace period has passed.
+pub async fn purge_departed(data: &RwLock<TypeMap>) {
+    let mut wlock = data.write().await;
+    let cutoff = Utc::now() - Duration::days(GRACE_PERIOD_DAYS);
+    let departed = wlock.get_mut::<DepartedGuilds>().expect("no departed guilds write data");
+    let expired: Vec<GuildId> = departed.iter()
+        .filter(|(_, left)| **left < cutoff)
+        .map(|(gid, _)| *gid)
+        .collect();
+    for gid in expired.iter() {
+        departed.remove(gid);
+    }
+    for gid in expired {
+        wlock.get_mut::<GameSuggestions>().expect("no suggestions write data").remove(&gid);
+        let players = wlock.get_mut::<PlayerContainer>().expect("no players write data");
+        for profile in players.iter_mut() {
+            profile.hidden.remove(&gid);
+            profile.left.remove(&gid);
+        }
+        wlock.get_mut::<GuildSettingsContainer>().expect("no settings write data").remove(&gid);
+        wlock.get_mut::<GameNightContainer>().expect("no game nights write data").remove(&gid);
+        info!("Purged data for guild {}", gid);
+    }
+}
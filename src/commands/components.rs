@@ -0,0 +1,263 @@
+use chrono::{Duration, Utc};
+use tracing::warn;
+
+use serenity::client::Context;
+use serenity::{
+    builder::CreateComponents,
+    framework::standard::CommandResult,
+    model::{
+        id::GuildId,
+        interactions::{
+            InteractionApplicationCommandCallbackDataFlags,
+            InteractionResponseType,
+            message_component::{ButtonStyle, MessageComponentInteraction},
+        },
+        misc::Mentionable,
+    },
+};
+
+use game_randomizer::polls::{StrawPoll, MAX_ANSWERS};
+use game_randomizer::suggestions::{SuggestionState, Vote};
+
+use crate::commands::{lifecycle, polls, suggestions, votes};
+use crate::commands::lifecycle::PickVetoes;
+
+/// How long a vetoed suggestion is left out of picks.
+const VETO_HOURS: i64 = 12;
+/// Longest custom id discord accepts on a component.
+const MAX_CUSTOM_ID: usize = 100;
+/// Most options a select menu can hold.
+const MAX_OPTIONS: usize = 25;
+/// Longest label or value of a select menu option.
+const MAX_OPTION_TEXT: usize = 100;
+const POLL_MENU_ID: &str = "common:poll";
+const UPVOTE_MENU_ID: &str = "list:upvote";
+const DOWNVOTE_MENU_ID: &str = "list:downvote";
+
+/// What a button under a random pick does.
+#[derive(Clone,Copy,PartialEq)]
+enum PickAction {
+    /// Pick another game instead.
+    Reroll,
+    /// Play it, moving it to the played history.
+    Accept,
+    /// Leave it out of picks for the night and pick another.
+    Veto,
+}
+impl PickAction {
+    fn name(&self) -> &'static str {
+        match self {
+            PickAction::Reroll => "reroll",
+            PickAction::Accept => "accept",
+            PickAction::Veto => "veto",
+        }
+    }
+    /// Custom id of the button, carrying the picked suggestion and the tags
+    /// it was picked with so rerolls keep to them. Tags that don't fit are
+    /// left off.
+    fn custom_id(&self, id: u32, tags: &[String]) -> String {
+        let mut custom_id = format!{"pick:{}:{}:", self.name(), id};
+        for tag in tags {
+            if custom_id.len() + tag.len() + 1 > MAX_CUSTOM_ID {
+                break;
+            }
+            if !custom_id.ends_with(':') {
+                custom_id.push(' ');
+            }
+            custom_id.push_str(tag);
+        }
+        custom_id
+    }
+    fn parse(custom_id: &str) -> Option<(PickAction, u32, Vec<String>)> {
+        let mut parts = custom_id.splitn(4, ':');
+        if parts.next() != Some("pick") {
+            return None;
+        }
+        let action = match parts.next()? {
+            "reroll" => PickAction::Reroll,
+            "accept" => PickAction::Accept,
+            "veto" => PickAction::Veto,
+            _ => return None
+        };
+        let id = parts.next()?.parse::<u32>().ok()?;
+        let tags = parts.next().unwrap_or("").split_whitespace().map(|t| t.to_string()).collect();
+        Some((action, id, tags))
+    }
+}
+
+/// Reroll, accept and veto buttons for a random pick.
+pub fn pick_buttons<'a>(c: &'a mut CreateComponents, id: u32, tags: &[String]) -> &'a mut CreateComponents {
+    c.create_action_row(|r| r
+        .create_button(|b| b
+            .style(ButtonStyle::Secondary)
+            .label("Reroll")
+            .custom_id(PickAction::Reroll.custom_id(id, tags)))
+        .create_button(|b| b
+            .style(ButtonStyle::Success)
+            .label("Accept")
+            .custom_id(PickAction::Accept.custom_id(id, tags)))
+        .create_button(|b| b
+            .style(ButtonStyle::Danger)
+            .label("Veto")
+            .custom_id(PickAction::Veto.custom_id(id, tags))))
+}
+
+fn option_text(text: &str) -> String {
+    text.chars().take(MAX_OPTION_TEXT).collect()
+}
+
+/// A menu to start a poll from some of the common games found.
+pub fn poll_menu<'a>(c: &'a mut CreateComponents, titles: &[String]) -> &'a mut CreateComponents {
+    let shown = titles.len().min(MAX_OPTIONS);
+    c.create_action_row(|r| r
+        .create_select_menu(|m| m
+            .custom_id(POLL_MENU_ID)
+            .placeholder("Start a poll from these games")
+            .min_values(2)
+            .max_values(shown.min(MAX_ANSWERS) as u64)
+            .options(|o| {
+                for title in titles.iter().take(shown) {
+                    o.create_option(|o| o.label(option_text(title)).value(option_text(title)));
+                }
+                o
+            })))
+}
+
+/// Menus to vote on the suggestions shown on a listing page, by id and
+/// title. Empty pages get none.
+pub fn vote_menus<'a>(c: &'a mut CreateComponents, entries: &[(u32, String)]) -> &'a mut CreateComponents {
+    if entries.is_empty() {
+        return c;
+    }
+    for (custom_id, placeholder) in [(UPVOTE_MENU_ID, "Upvote a suggestion"), (DOWNVOTE_MENU_ID, "Downvote a suggestion")] {
+        c.create_action_row(|r| r
+            .create_select_menu(|m| m
+                .custom_id(custom_id)
+                .placeholder(placeholder)
+                .options(|o| {
+                    for (id, title) in entries.iter().take(MAX_OPTIONS) {
+                        o.create_option(|o| o.label(option_text(&format!{"#{} {}", id, title})).value(id));
+                    }
+                    o
+                })));
+    }
+    c
+}
+
+/// Answers clicks on pick buttons and choices in common games and listing
+/// menus.
+pub async fn handle(ctx: &Context, component: &MessageComponentInteraction) {
+    let gid = match component.guild_id {
+        Some(g) => g,
+        None => return
+    };
+    let result = match component.data.custom_id.as_str() {
+        POLL_MENU_ID => start_poll(ctx, component).await,
+        UPVOTE_MENU_ID => vote(ctx, component, gid, Vote::Up).await,
+        DOWNVOTE_MENU_ID => vote(ctx, component, gid, Vote::Down).await,
+        custom_id => match PickAction::parse(custom_id) {
+            Some((action, id, tags)) => pick_action(ctx, component, gid, action, id, tags).await,
+            None => Ok(())
+        }
+    };
+    if let Err(why) = result {
+        warn!("Failed answering component: {:?}", why);
+    }
+}
+
+/// Answers only the user who clicked.
+async fn ephemeral(ctx: &Context, component: &MessageComponentInteraction, content: impl ToString) -> CommandResult {
+    component.create_interaction_response(ctx, |r| r
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|d| d
+            .content(content)
+            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL))
+    ).await?;
+    Ok(())
+}
+
+/// Casts the clicking user's vote on the suggestion chosen in a listing menu.
+async fn vote(ctx: &Context, component: &MessageComponentInteraction, gid: GuildId, vote: Vote) -> CommandResult {
+    let id = match component.data.values.first().and_then(|v| v.parse::<u32>().ok()) {
+        Some(i) => i,
+        None => return Ok(())
+    };
+    match votes::cast(ctx, gid, id, component.user.id, Some(vote)).await {
+        Some(score) => ephemeral(ctx, component, format!{"Suggestion #{} now has a score of {}.", id, score}).await,
+        None => ephemeral(ctx, component, "That suggestion no longer exists.").await,
+    }
+}
+
+/// Takes the buttons off a pick, noting what became of it.
+async fn settle(ctx: &Context, component: &MessageComponentInteraction, note: String) -> CommandResult {
+    let content = format!{"{}\n{}", component.message.content, note};
+    component.create_interaction_response(ctx, |r| r
+        .kind(InteractionResponseType::UpdateMessage)
+        .interaction_response_data(|d| d
+            .content(content)
+            .components(|c| c))
+    ).await?;
+    Ok(())
+}
+
+async fn pick_action(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    gid: GuildId,
+    action: PickAction,
+    id: u32,
+    tags: Vec<String>,
+) -> CommandResult {
+    let user = component.user.id;
+    if action == PickAction::Accept {
+        return match lifecycle::mark(ctx, gid, id, SuggestionState::Played, user).await {
+            Ok(response) => settle(ctx, component, response).await,
+            Err(why) => ephemeral(ctx, component, why).await
+        };
+    }
+    // vetoes count even when nothing is left to pick instead
+    let note = if action == PickAction::Veto {
+        let vetoes = {
+            let rlock = ctx.data.read().await;
+            rlock.get::<PickVetoes>().expect("no pick vetoes stored").clone()
+        };
+        let until = Utc::now() + Duration::hours(VETO_HOURS);
+        vetoes.lock().await.entry(gid).or_default().veto(id, until);
+        format!{"Vetoed for tonight by {}.", user.mention()}
+    } else {
+        format!{"Rerolled by {}.", user.mention()}
+    };
+    let picked = suggestions::pick(ctx, gid, &tags, &[id]).await;
+    let (new_id, response) = match picked {
+        Ok(p) => p,
+        Err(why) if action == PickAction::Veto => return settle(ctx, component, format!{"{} {}", note, why}).await,
+        Err(why) => return ephemeral(ctx, component, why).await
+    };
+    settle(ctx, component, note).await?;
+    let posted = component.channel_id.send_message(ctx, |m| m
+        .content(response)
+        .components(|c| pick_buttons(c, new_id, &tags))
+    ).await?;
+    votes::track(ctx, &posted, gid, new_id).await?;
+    lifecycle::track_pick(ctx, &posted, gid, new_id).await?;
+    Ok(())
+}
+
+async fn start_poll(ctx: &Context, component: &MessageComponentInteraction) -> CommandResult {
+    let mut poll = StrawPoll::new(component.user.id, "What should we play?");
+    for title in component.data.values.iter() {
+        if let Err(why) = poll.add_answer(title.clone()) {
+            return ephemeral(ctx, component, why).await;
+        }
+    }
+    if !poll.is_ready() {
+        return ephemeral(ctx, component, format!{"Polls need between 2 and {} answers.", MAX_ANSWERS}).await;
+    }
+    component.create_interaction_response(ctx, |r| r
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|d| d.content(polls::render(&poll)))
+    ).await?;
+    let posted = component.get_interaction_response(ctx).await?;
+    polls::open(ctx, &posted, poll).await
+}
+
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{DateTime, Duration, Utc};
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use tracing::{info, warn};
+
+use serenity::prelude::*;
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        Args,
+        CommandResult
+    },
+    model::{
+        channel::{Message, Reaction, ReactionType},
+        id::{GuildId, UserId},
+        misc::Mentionable,
+    }
+};
+
+use game_randomizer::availability::{best_windows, Member};
+use game_randomizer::gamenights::{GameNight, GameNightBook, Recurrence, Schedule, StartAction};
+use game_randomizer::polls::{StrawPoll, MAX_ANSWERS};
+use game_randomizer::suggestions::parse_id;
+
+use crate::commands::permissions::author_is_moderator;
+use crate::commands::{components, lifecycle, players, polls, suggestions, votes};
+use crate::commands::players::PlayerContainer;
+
+pub const RSVP_EMOJI: &str = "✅";
+/// Minutes before a game night starts that the people coming are reminded.
+const REMINDER_MINUTES: i64 = 30;
+/// Game nights whose start was missed by more than this many minutes,
+/// while the bot was offline, are skipped rather than started late.
+const LATE_START_MINUTES: i64 = 60;
+/// Seconds between checks for reminders and starts.
+const TICK_SECONDS: u64 = 60;
+/// Most windows suggested for a game night.
+const MAX_WINDOWS: usize = 3;
+
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Deserialize,Serialize)]
+pub struct GameNightContainer;
+impl TypeMapKey for GameNightContainer {
+    type Value = HashMap<GuildId, GameNightBook>;
+}
+
+#[group]
+#[prefixes("gamenight")]
+#[only_in(guilds)]
+#[default_command(list)]
+#[commands(create, list, cancel, windows)]
+pub struct GameNights;
+
+/// Discord timestamp markup, shown to everyone in their own time zone.
+fn when(start: &DateTime<Utc>) -> String {
+    format!{"<t:{0}:F> (<t:{0}:R>)", start.timestamp()}
+}
+
+fn describe(night: &GameNight) -> String {
+    let mut line = format!{"**Game night #{}** · {}", night.id, when(&night.start)};
+    if night.recurrence == Some(Recurrence::Weekly) {
+        line.push_str(" · every week");
+    }
+    line
+}
+
+// ~gamenight create Friday 20:00 [recurring weekly] [poll|pick], in your time zone
+#[command]
+async fn create(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let words: Vec<String> = args.iter::<String>().flatten().collect();
+    let zone = players::zone_of(ctx, msg.author.id).await;
+    let schedule = match Schedule::parse(&words, Utc::now(), zone) {
+        Ok(s) => s,
+        Err(why) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
+    };
+    let night = {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<GameNightContainer>().expect("no game nights write data");
+        winner.entry(gid).or_default().add(msg.author.id, msg.channel_id, schedule).clone()
+    };
+    announce(ctx, gid, &night).await
+}
+
+// ~gamenight list
+#[command]
+async fn list(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let lines: Vec<String> = {
+        let rlock = ctx.data.read().await;
+        let inner = rlock.get::<GameNightContainer>().expect("no game nights read data");
+        match inner.get(&gid) {
+            Some(book) => book.upcoming().into_iter()
+                .map(|n| format!{"{} · {} going", describe(n), n.rsvps.len()})
+                .collect(),
+            None => Vec::new()
+        }
+    };
+    if lines.is_empty() {
+        msg.reply(ctx, "No game nights scheduled, create one with: gamenight create Friday 20:00").await?;
+    } else {
+        msg.channel_id.say(ctx, lines.join("\n")).await?;
+    }
+    Ok(())
+}
+
+// ~gamenight cancel #3
+#[command]
+async fn cancel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(i) => i,
+        None => {
+            msg.reply(ctx, "Usage: gamenight cancel #id").await?;
+            return Ok(());
+        }
+    };
+    let moderator = author_is_moderator(ctx, msg).await;
+    let response = {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<GameNightContainer>().expect("no game nights write data");
+        let book = winner.entry(gid).or_default();
+        match book.get(id) {
+            Some(n) if n.creator != msg.author.id && !moderator => "Only the creator or a moderator can cancel this game night.".to_string(),
+            Some(_) => {
+                book.remove(id);
+                format!{"Cancelled game night #{}.", id}
+            },
+            None => "No game night with that id.".to_string()
+        }
+    };
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
+
+// ~gamenight windows #3, when most of those coming can play this week
+#[command]
+async fn windows(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let id = match args.single::<String>().ok().and_then(|a| parse_id(&a)) {
+        Some(i) => i,
+        None => {
+            msg.reply(ctx, "Usage: gamenight windows #id").await?;
+            return Ok(());
+        }
+    };
+    let rsvps: Vec<UserId> = {
+        let rlock = ctx.data.read().await;
+        let inner = rlock.get::<GameNightContainer>().expect("no game nights read data");
+        match inner.get(&gid).and_then(|b| b.get(id)) {
+            Some(n) => n.rsvps.iter().copied().collect(),
+            None => {
+                msg.reply(ctx, "No game night with that id.").await?;
+                return Ok(());
+            }
+        }
+    };
+    let (found, undeclared) = {
+        let rlock = ctx.data.read().await;
+        let players = rlock.get::<PlayerContainer>().expect("no player container found");
+        let (declared, undeclared): (Vec<UserId>, Vec<UserId>) = rsvps.iter()
+            .partition(|u| players.get(**u).is_some_and(|p| !p.availability.is_empty()));
+        let members: Vec<Member> = declared.iter()
+            .filter_map(|u| players.get(*u))
+            .map(|p| Member { user: p.user, zone: p.zone(), availability: &p.availability })
+            .collect();
+        (best_windows(&members, Utc::now(), MAX_WINDOWS), undeclared)
+    };
+    if found.is_empty() {
+        msg.reply(ctx, "Nobody coming has said when they can play, they can with: available fri 19:00-23:00").await?;
+        return Ok(());
+    }
+    let mut lines = vec![format!{"When those coming to game night #{} can play this week:", id}];
+    for window in found {
+        let users: Vec<UserId> = window.available.into_iter().collect();
+        lines.push(format!{"<t:{}:F> to <t:{}:t> · {}/{} · {}",
+            window.start.timestamp(), window.end.timestamp(), users.len(), rsvps.len(), mentions(&users)});
+    }
+    if !undeclared.is_empty() {
+        lines.push(format!{"Not declared: {}", mentions(&undeclared)});
+    }
+    msg.channel_id.send_message(ctx, |m| m
+        .content(lines.join("\n"))
+        .allowed_mentions(|a| a.empty_users())
+    ).await?;
+    Ok(())
+}
+
+/// Posts a game night claimed for announcing for members to RSVP to and
+/// remembers the message, or leaves it to be announced again on failure.
+async fn announce(ctx: &Context, gid: GuildId, night: &GameNight) -> CommandResult {
+    let start = match night.action {
+        StartAction::Poll => "Starts with a poll between the common games of everyone coming.",
+        StartAction::Pick => "Starts with a random pick from the common games of everyone coming.",
+    };
+    let content = format!{"{}\n{}\nReact with {} to join.", describe(night), start, RSVP_EMOJI};
+    let posted = night.channel.say(ctx, content).await;
+    if let Ok(message) = &posted {
+        if let Err(why) = message.react(ctx, ReactionType::Unicode(RSVP_EMOJI.to_string())).await {
+            warn!("Failed reacting to game night {}: {:?}", night.id, why);
+        }
+    }
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<GameNightContainer>().expect("no game nights write data");
+    if let Some(book) = winner.get_mut(&gid) {
+        book.announced(night.id, posted.as_ref().ok().map(|m| m.id));
+    }
+    posted?;
+    Ok(())
+}
+
+/// Adds or removes an RSVP when someone reacts to a game night.
+pub async fn handle_reaction(ctx: &Context, reaction: &Reaction, added: bool) {
+    let (gid, user) = match (reaction.guild_id, reaction.user_id) {
+        (Some(g), Some(u)) if u != ctx.cache.current_user_id().await => (g, u),
+        _ => return
+    };
+    if !matches!(&reaction.emoji, ReactionType::Unicode(e) if e == RSVP_EMOJI) {
+        return;
+    }
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<GameNightContainer>().expect("no game nights write data");
+    if let Some(night) = winner.get_mut(&gid).and_then(|b| b.by_message(reaction.message_id)) {
+        if added {
+            night.rsvps.insert(user);
+        } else {
+            night.rsvps.remove(&user);
+        }
+    }
+}
+
+/// Starts checking for reminders and game nights starting, once for as
+/// long as the bot runs.
+pub fn start_scheduler(ctx: &Context) {
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(TICK_SECONDS));
+        loop {
+            interval.tick().await;
+            tick(&ctx).await;
+        }
+    });
+}
+
+async fn tick(ctx: &Context) {
+    let now = Utc::now();
+    let mut reminders = Vec::new();
+    let mut started = Vec::new();
+    let mut unannounced = Vec::new();
+    {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<GameNightContainer>().expect("no game nights write data");
+        for (gid, book) in winner.iter_mut() {
+            reminders.extend(book.due_reminders(now, Duration::minutes(REMINDER_MINUTES)));
+            started.extend(book.take_started(now).into_iter().map(|n| (*gid, n)));
+            unannounced.extend(book.claim_unannounced().into_iter().map(|n| (*gid, n)));
+        }
+    }
+    for night in reminders {
+        if let Err(why) = remind(ctx, &night, now).await {
+            warn!("Failed reminding of game night {}: {:?}", night.id, why);
+        }
+    }
+    for (gid, night) in started {
+        if now - night.start > Duration::minutes(LATE_START_MINUTES) {
+            info!("Skipped game night {} in guild {} that started while offline", night.id, gid);
+            continue;
+        }
+        if let Err(why) = begin(ctx, gid, &night).await {
+            warn!("Failed starting game night {}: {:?}", night.id, why);
+        }
+    }
+    // recurring nights that moved on, and announcements that failed before
+    for (gid, night) in unannounced {
+        if let Err(why) = announce(ctx, gid, &night).await {
+            warn!("Failed announcing game night {}: {:?}", night.id, why);
+        }
+    }
+}
+
+fn mentions(users: &[UserId]) -> String {
+    users.iter().map(|u| u.mention().to_string()).collect::<Vec<String>>().join(" ")
+}
+
+async fn remind(ctx: &Context, night: &GameNight, now: DateTime<Utc>) -> CommandResult {
+    let minutes = (night.start - now).num_minutes().max(1);
+    let users: Vec<UserId> = night.rsvps.iter().copied().collect();
+    let content = if users.is_empty() {
+        format!{"Game night #{} starts in {} minutes, react with {} on its announcement to join.", night.id, minutes, RSVP_EMOJI}
+    } else {
+        format!{"Game night #{} starts in {} minutes! {}", night.id, minutes, mentions(&users)}
+    };
+    night.channel.say(ctx, content).await?;
+    Ok(())
+}
+
+/// Opens a game night with a poll or pick between the common games of
+/// those coming, or a random suggestion when they have too few in common.
+async fn begin(ctx: &Context, gid: GuildId, night: &GameNight) -> CommandResult {
+    let users: Vec<UserId> = night.rsvps.iter().copied().collect();
+    let header = format!{"Game night #{} is starting! {}", night.id, mentions(&users)};
+    let titles = match players::find_common(ctx, gid, &users).await {
+        Ok(found) => found.titles,
+        Err(_) => Vec::new()
+    };
+    match night.action {
+        StartAction::Poll if titles.len() >= 2 => {
+            let chosen: Vec<String> = titles.choose_multiple(&mut rand::thread_rng(), MAX_ANSWERS).cloned().collect();
+            let mut poll = StrawPoll::new(night.creator, "What should we play tonight?");
+            for title in chosen {
+                poll.add_answer(title)?;
+            }
+            night.channel.say(ctx, header).await?;
+            let posted = night.channel.say(ctx, polls::render(&poll)).await?;
+            polls::open(ctx, &posted, poll).await?;
+        },
+        _ if !titles.is_empty() => {
+            let title = titles.choose(&mut rand::thread_rng()).cloned().expect("titles not empty");
+            night.channel.say(ctx, format!{"{}\nTonight's game: **{}**", header, title}).await?;
+        },
+        _ => match suggestions::pick(ctx, gid, &[], &[]).await {
+            Ok((id, response)) => {
+                let posted = night.channel.send_message(ctx, |m| m
+                    .content(format!{"{}\n{}", header, response})
+                    .components(|c| components::pick_buttons(c, id, &[]))
+                ).await?;
+                votes::track(ctx, &posted, gid, id).await?;
+                lifecycle::track_pick(ctx, &posted, gid, id).await?;
+            },
+            Err(_) => {
+                night.channel.say(ctx, format!{"{}\nThere are no common games or suggestions to pick from.", header}).await?;
+            }
+        }
+    };
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::fs::File;
 use std::path::Path;
-use std::io::prelude::*;
+use serde::Serialize;
 //use serenity::prelude::*;
 use serenity::client::Context;
 use serenity::model::channel::{MessageType, Message};
@@ -12,7 +12,8 @@ use serenity::framework::standard::{
 use serenity::utils::MessageBuilder;
 
 use crate::ShardManagerContainer;
-use crate::commands::{GameSuggestions, PlayerContainer};
+use crate::commands::{GameSuggestions, PlayerContainer, GuildSettingsContainer, GameNightContainer};
+use crate::commands::cleanup::DepartedGuilds;
 
 #[group]
 #[commands(ping, quit, save, get_ids)]
@@ -89,38 +90,30 @@ async fn save(ctx: &Context, msg: &Message) -> CommandResult {
             return Ok(());
         }
     };
-    match File::create(storage.join("suggestions.json")) {
-        Err(_) => {
-            msg.reply(ctx, "Failure opening suggestions file").await?;
+    let stores: [(&str, Result<(), ()>); 5] = [
+        ("suggestions", write_store(storage, "suggestions", data.get::<GameSuggestions>().expect("no suggestions read data"))),
+        ("players", write_store(storage, "players", data.get::<PlayerContainer>().expect("no players read data"))),
+        ("settings", write_store(storage, "settings", data.get::<GuildSettingsContainer>().expect("no settings read data"))),
+        ("departed", write_store(storage, "departed", data.get::<DepartedGuilds>().expect("no departed guilds read data"))),
+        ("gamenights", write_store(storage, "gamenights", data.get::<GameNightContainer>().expect("no game nights read data"))),
+    ];
+    for (name, result) in stores.iter() {
+        if result.is_err() {
+            msg.reply(ctx, format!{"Failure saving {} file.", name}).await?;
             return Ok(());
-        },
-        Ok(p) => {
-            let inner = data.get::<GameSuggestions>().expect("no suggestions read data");
-            match serde_json::to_writer(p, inner) {
-                Ok(_) => {},
-                Err(_) => {
-                    msg.reply(ctx, "Error serializing game suggestions.").await?;
-                    return Ok(());
-                }
-            };
-        }
-    };
-    match File::create(storage.join("players.json")) {
-        Err(_) => {
-            msg.reply(ctx, "Failure opening suggestions file").await?;
-            return Ok(());
-        },
-        Ok(p) => {
-            let inner = data.get::<PlayerContainer>().expect("no players read data");
-            match serde_json::to_writer(p, inner) {
-                Ok(_) => {},
-                Err(_) => {
-                    msg.reply(ctx, "Error serializing players.").await?;
-                    return Ok(());
-                }
-            };
         }
-    };
+    }
     msg.reply(ctx, "Saving successful!").await?;
     Ok(())
 }
+
+fn write_store<T: Serialize>(storage: &Path, name: &str, value: &T) -> Result<(), ()> {
+    let file = match File::create(storage.join(format!{"{}.json", name})) {
+        Ok(f) => f,
+        Err(_) => return Err(())
+    };
+    match serde_json::to_writer(file, value) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(())
+    }
+}
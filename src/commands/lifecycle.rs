@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use serenity::prelude::*;
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        Args,
+        CommandResult
+    },
+    model::{
+        channel::{Message, Reaction, ReactionType},
+        id::{GuildId, MessageId, UserId},
+    }
+};
+
+use game_randomizer::picker::Vetoes;
+use game_randomizer::suggestions::SuggestionState;
+
+use crate::commands::suggestions::GameSuggestions;
+use crate::commands::permissions::author_is_moderator;
+
+pub const PLAYED_EMOJI: &str = "✅";
+/// Number of picks that can still be marked as played with a reaction.
+const MAX_TRACKED: usize = 100;
+
+/// Random picks that move to played once someone reacts to them.
+pub struct PickMessages;
+impl TypeMapKey for PickMessages {
+    type Value = Arc<Mutex<HashMap<MessageId, (GuildId, u32)>>>;
+}
+
+/// Suggestions vetoed from picks in each guild for the night.
+pub struct PickVetoes;
+impl TypeMapKey for PickVetoes {
+    type Value = Arc<Mutex<HashMap<GuildId, Vetoes>>>;
+}
+
+/// Adds the played reaction to a random pick and watches for it being used.
+pub async fn track_pick(ctx: &Context, message: &Message, gid: GuildId, id: u32) -> CommandResult {
+    message.react(ctx, ReactionType::Unicode(PLAYED_EMOJI.to_string())).await?;
+    let tracked = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<PickMessages>().expect("no pick messages stored").clone()
+    };
+    let mut tracked = tracked.lock().await;
+    tracked.insert(message.id, (gid, id));
+    if tracked.len() > MAX_TRACKED {
+        if let Some(oldest) = tracked.keys().min().copied() {
+            tracked.remove(&oldest);
+        }
+    }
+    Ok(())
+}
+
+/// Moves a suggestion to a new state, returning a message for the user.
+pub async fn mark(ctx: &Context, gid: GuildId, id: u32, state: SuggestionState, by: UserId) -> Result<String, String> {
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+    let suggestion = match winner.get_mut(&gid).and_then(|b| b.get_mut(id)) {
+        Some(s) => s,
+        None => return Err("No suggestion with that id.".to_string())
+    };
+    suggestion.transition(state, by)?;
+    Ok(format!{"{} is now {}.", suggestion.title(), state})
+}
+
+/// Marks a tracked pick as played when someone reacts to it.
+pub async fn handle_reaction(ctx: &Context, reaction: &Reaction) {
+    let user = match reaction.user_id {
+        Some(u) if u != ctx.cache.current_user_id().await => u,
+        _ => return
+    };
+    if !matches!(&reaction.emoji, ReactionType::Unicode(e) if e == PLAYED_EMOJI) {
+        return;
+    }
+    let target = {
+        let rlock = ctx.data.read().await;
+        let tracked = rlock.get::<PickMessages>().expect("no pick messages stored").clone();
+        let mut tracked = tracked.lock().await;
+        tracked.remove(&reaction.message_id)
+    };
+    if let Some((gid, id)) = target {
+        if let Ok(response) = mark(ctx, gid, id, SuggestionState::Played, user).await {
+            let _ = reaction.channel_id.say(ctx, response).await;
+        }
+    }
+}
+
+#[group]
+#[only_in(guilds)]
+#[commands(mark_suggestion)]
+pub struct Lifecycle;
+
+// ~mark #12 played|completed|dropped|backlog
+#[command]
+#[aliases("mark")]
+async fn mark_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let id = args.single::<String>().ok().and_then(|a| a.trim_start_matches('#').parse::<u32>().ok());
+    let state = args.single::<String>().map(|s| s.parse::<SuggestionState>());
+    let (id, state) = match (id, state) {
+        (Some(i), Ok(Ok(s))) => (i, s),
+        (Some(_), Ok(Err(why))) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        },
+        _ => {
+            msg.reply(ctx, "Usage: mark #id played|completed|dropped|backlog").await?;
+            return Ok(());
+        }
+    };
+    let moderator = author_is_moderator(ctx, msg).await;
+    let owner = {
+        let rlock = ctx.data.read().await;
+        let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
+        inner.get(&gid).and_then(|b| b.get(id)).map(|s| s.user())
+    };
+    let response = match owner {
+        Some(o) if o != msg.author.id && !moderator => "Found suggestion but cannot be changed by you.".to_string(),
+        _ => match mark(ctx, gid, id, state, msg.author.id).await {
+            Ok(r) => r,
+            Err(why) => why
+        }
+    };
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
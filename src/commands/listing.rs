@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+use serenity::prelude::*;
+use serenity::client::Context;
+use serenity::{
+    builder::CreateEmbed,
+    framework::standard::{Args, CommandResult},
+    model::{
+        channel::{Message, Reaction, ReactionType},
+        id::{GuildId, MessageId, UserId},
+        misc::Mentionable,
+    }
+};
+
+use game_randomizer::suggestions::{Filter, SuggestedGame, Suggestion};
+use game_randomizer::tags::normalize_tag;
+
+use crate::commands::suggestions::GameSuggestions;
+use crate::commands::components;
+
+pub const PAGE_SIZE: usize = 10;
+pub const PREV_EMOJI: &str = "◀️";
+pub const NEXT_EMOJI: &str = "▶️";
+/// Number of listings that can still be paged through at once.
+const MAX_SESSIONS: usize = 50;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum SortOrder {
+    Date,
+    Title,
+    Suggester,
+    Votes,
+}
+impl FromStr for SortOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "date" | "new" => Ok(SortOrder::Date),
+            "title" | "name" => Ok(SortOrder::Title),
+            "suggester" | "user" => Ok(SortOrder::Suggester),
+            "votes" | "top" => Ok(SortOrder::Votes),
+            _ => Err(format!{"Invalid sort '{}'. Try 'date', 'title', 'suggester' or 'votes'.", s})
+        }
+    }
+}
+
+/// How a suggestion listing is sorted and filtered.
+#[derive(Clone,Debug)]
+pub struct ListingQuery {
+    pub sort: SortOrder,
+    pub filter: Filter,
+}
+impl Default for ListingQuery {
+    fn default() -> Self {
+        ListingQuery {
+            sort: SortOrder::Date,
+            filter: Filter::default()
+        }
+    }
+}
+impl ListingQuery {
+    /// Parses `--sort <order>`, `--type <plain|steam>`, `--genre <genre>`,
+    /// `--state <state>`, `--all` and any number of `--tag <tag>`.
+    pub fn parse(args: &mut Args) -> Result<Self, String> {
+        let mut query = ListingQuery::default();
+        while !args.is_empty() {
+            let flag = args.single::<String>().map_err(|_| "Invalid listing option.".to_string())?;
+            if flag == "--all" {
+                query.filter.all = true;
+                continue;
+            }
+            let value = match args.single_quoted::<String>() {
+                Ok(v) => v,
+                Err(_) => return Err(format!{"Missing value for {}.", flag})
+            };
+            match flag.as_str() {
+                "--sort" => query.sort = value.parse()?,
+                "--type" => query.filter.kind = Some(value.parse()?),
+                "--genre" => query.filter.genre = Some(value.to_ascii_lowercase()),
+                "--tag" => query.filter.tags.extend(normalize_tag(&value)),
+                "--state" => query.filter.state = Some(value.parse()?),
+                _ => return Err(format!{"Unknown option '{}'. Try --sort, --type, --genre, --tag, --state or --all.", flag})
+            };
+        }
+        Ok(query)
+    }
+    fn describe(&self) -> String {
+        let filter = &self.filter;
+        let mut description = format!{"sorted by {:?}", self.sort}.to_ascii_lowercase();
+        if let Some(kind) = filter.kind {
+            description.push_str(&format!{" · {} only", kind});
+        }
+        if let Some(genre) = &filter.genre {
+            description.push_str(" · genre ");
+            description.push_str(genre);
+        }
+        match filter.state {
+            Some(state) => description.push_str(&format!{" · {} only", state}),
+            None if filter.all => description.push_str(" · all states"),
+            None => {}
+        };
+        if !filter.tags.is_empty() {
+            description.push_str(" · tagged ");
+            description.push_str(&filter.tags.join(", "));
+        }
+        description
+    }
+}
+
+/// A posted listing that can still be paged through with reactions.
+pub struct Listing {
+    pub guild: GuildId,
+    pub query: ListingQuery,
+    pub page: usize,
+}
+
+pub struct ListingSessions;
+impl TypeMapKey for ListingSessions {
+    type Value = Arc<Mutex<HashMap<MessageId, Listing>>>;
+}
+
+/// A rendered page of a listing, ready to be put into an embed.
+pub struct Page {
+    pub body: String,
+    /// The id and title of each suggestion on the page.
+    pub entries: Vec<(u32, String)>,
+    pub page: usize,
+    pub pages: usize,
+    pub footer: String,
+}
+impl Page {
+    pub fn embed<'a>(&self, e: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+        e.title("Currently suggested games")
+            .description(&self.body)
+            .footer(|f| f.text(format!{"Page {}/{} · {}", self.page + 1, self.pages, self.footer}))
+    }
+}
+
+/// Sort keys and the rendered line for one suggestion in a listing.
+struct Entry {
+    id: u32,
+    name: String,
+    user: UserId,
+    created: DateTime<Utc>,
+    title: String,
+    score: i32,
+    line: String,
+}
+impl From<&Suggestion> for Entry {
+    fn from(suggestion: &Suggestion) -> Self {
+        Entry {
+            id: suggestion.id,
+            name: suggestion.title().to_string(),
+            user: suggestion.user(),
+            created: suggestion.created,
+            title: suggestion.title().to_ascii_lowercase(),
+            score: suggestion.score(),
+            line: line(suggestion)
+        }
+    }
+}
+
+fn line(suggestion: &Suggestion) -> String {
+    let mut line = format!{"**#{}** ", suggestion.id};
+    if !suggestion.votes.is_empty() {
+        line.push_str(&format!{"({:+}) ", suggestion.score()});
+    }
+    match &suggestion.game {
+        SuggestedGame::Store(app) => line.push_str(&app.to_string()),
+        SuggestedGame::PlainText(app) => {
+            match &app.url {
+                Some(url) => line.push_str(&format!{"[{}]({})", app.title, url}),
+                None => line.push_str(&app.title),
+            };
+            if let Some(genre) = &app.genre {
+                line.push_str(" · ");
+                line.push_str(genre);
+            }
+        }
+    };
+    if !suggestion.state.is_active() {
+        line.push_str(&format!{" · *{}*", suggestion.state});
+    }
+    if !suggestion.tags.is_empty() {
+        line.push_str(" · `");
+        line.push_str(&suggestion.tags.iter().map(|t| t.as_str()).collect::<Vec<&str>>().join(" "));
+        line.push('`');
+    }
+    line.push_str(" · ");
+    line.push_str(&suggestion.user().mention().to_string());
+    line
+}
+
+/// Renders the requested page of a guild's suggestions, clamping the page
+/// to those available.
+pub async fn render(ctx: &Context, listing: &Listing) -> Page {
+    // snapshot so the lock isn't held for name lookups
+    let mut entries: Vec<Entry> = {
+        let rlock = ctx.data.read().await;
+        let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
+        match inner.get(&listing.guild) {
+            Some(existing) => existing.list(&listing.query.filter).into_iter()
+                .map(Entry::from)
+                .collect(),
+            None => Vec::new()
+        }
+    };
+    match listing.query.sort {
+        SortOrder::Date => entries.sort_by_key(|e| e.created),
+        SortOrder::Title => entries.sort_by(|a, b| a.title.cmp(&b.title)),
+        SortOrder::Suggester => {
+            let users: HashSet<UserId> = entries.iter().map(|e| e.user).collect();
+            let mut names = HashMap::new();
+            for user in users {
+                let name = match ctx.cache.member(listing.guild, user).await {
+                    Some(m) => m.display_name().to_ascii_lowercase(),
+                    None => user.to_string()
+                };
+                names.insert(user, name);
+            }
+            entries.sort_by(|a, b| names[&a.user].cmp(&names[&b.user]).then(a.created.cmp(&b.created)));
+        },
+        SortOrder::Votes => entries.sort_by(|a, b| b.score.cmp(&a.score).then(a.created.cmp(&b.created))),
+    };
+    let pages = std::cmp::max(1, entries.len().div_ceil(PAGE_SIZE));
+    let page = std::cmp::min(listing.page, pages - 1);
+    let shown: Vec<&Entry> = entries.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE).collect();
+    let body = if shown.is_empty() {
+        "No suggestions found.".to_string()
+    } else {
+        shown.iter()
+            .map(|e| e.line.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    };
+    let entries = shown.iter().map(|e| (e.id, e.name.clone())).collect();
+    Page { body, entries, page, pages, footer: listing.query.describe() }
+}
+
+/// Posts the first page of a listing in reply to a message, with menus to
+/// vote on its suggestions and paging reactions when there is more than
+/// one page.
+pub async fn post(ctx: &Context, msg: &Message, listing: Listing) -> CommandResult {
+    let page = render(ctx, &listing).await;
+    let mut reactions = Vec::new();
+    if page.pages > 1 {
+        reactions.push(ReactionType::Unicode(PREV_EMOJI.to_string()));
+        reactions.push(ReactionType::Unicode(NEXT_EMOJI.to_string()));
+    }
+    let posted = msg.channel_id.send_message(ctx, |m| m
+        .reference_message(msg)
+        .embed(|e| page.embed(e))
+        .components(|c| components::vote_menus(c, &page.entries))
+        .reactions(reactions)
+    ).await?;
+    if page.pages > 1 {
+        track(ctx, posted.id, listing).await;
+    }
+    Ok(())
+}
+
+/// Remembers a posted listing so reactions on it can change pages.
+pub async fn track(ctx: &Context, message: MessageId, listing: Listing) {
+    let sessions = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<ListingSessions>().expect("no listing sessions stored").clone()
+    };
+    let mut sessions = sessions.lock().await;
+    sessions.insert(message, listing);
+    if sessions.len() > MAX_SESSIONS {
+        if let Some(oldest) = sessions.keys().min().copied() {
+            sessions.remove(&oldest);
+        }
+    }
+}
+
+/// Moves a tracked listing to the previous or next page.
+pub async fn handle_reaction(ctx: &Context, reaction: &Reaction) {
+    let user = match reaction.user_id {
+        Some(u) if u != ctx.cache.current_user_id().await => u,
+        _ => return
+    };
+    let step: isize = match &reaction.emoji {
+        ReactionType::Unicode(e) if e == PREV_EMOJI => -1,
+        ReactionType::Unicode(e) if e == NEXT_EMOJI => 1,
+        _ => return
+    };
+    let sessions = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<ListingSessions>().expect("no listing sessions stored").clone()
+    };
+    let mut sessions = sessions.lock().await;
+    let listing = match sessions.get_mut(&reaction.message_id) {
+        Some(l) => l,
+        None => return
+    };
+    listing.page = match listing.page.checked_add_signed(step) {
+        Some(p) => p,
+        None => return
+    };
+    let page = render(ctx, listing).await;
+    listing.page = page.page;
+    let _ = reaction.channel_id.edit_message(ctx, reaction.message_id, |m| m
+        .embed(|e| page.embed(e))
+        .components(|c| components::vote_menus(c, &page.entries))
+    ).await;
+    // needs Manage Messages, paging still works without it
+    let _ = reaction.channel_id
+        .delete_reaction(ctx, reaction.message_id, Some(user), reaction.emoji.clone())
+        .await;
+}
@@ -1,6 +1,18 @@
 pub mod general;
 pub mod suggestions;
 pub mod players;
+pub mod settings;
+pub mod permissions;
+pub mod polls;
+pub mod listing;
+pub mod votes;
+pub mod tags;
+pub mod lifecycle;
+pub mod cleanup;
+pub mod titles;
+pub mod slash;
+pub mod components;
+pub mod gamenights;
 
 pub use self::general::{
     GENERAL_GROUP
@@ -11,6 +23,28 @@ pub use self::suggestions::{
 };
 pub use self::players::{
     PLAYERS_GROUP,
-    Player,
     PlayerContainer
 };
+pub use self::settings::{
+    SETTINGS_GROUP,
+    GuildSettingsContainer
+};
+pub use self::polls::{
+    POLLS_GROUP,
+    StrawPollKey
+};
+pub use self::votes::{
+    VOTES_GROUP,
+    VoteMessages
+};
+pub use self::tags::TAGS_GROUP;
+pub use self::titles::TITLES_GROUP;
+pub use self::lifecycle::{
+    LIFECYCLE_GROUP,
+    PickMessages,
+    PickVetoes
+};
+pub use self::gamenights::{
+    GAMENIGHTS_GROUP,
+    GameNightContainer
+};
@@ -0,0 +1,65 @@
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{check, hook},
+        Args,
+        CommandOptions,
+        DispatchError,
+        Reason
+    },
+    model::{
+        channel::Message,
+        id::{GuildId, UserId},
+    }
+};
+
+use crate::commands::settings::guild_settings;
+
+/// Whether the user moderates the guild, either through the guild's
+/// configured moderator role or the Manage Guild permission.
+pub async fn is_moderator(ctx: &Context, gid: GuildId, user: UserId) -> bool {
+    let member = match gid.member(ctx, user).await {
+        Ok(m) => m,
+        Err(_) => return false
+    };
+    if let Some(role) = guild_settings(ctx, Some(gid)).await.moderator_role {
+        if member.roles.contains(&role) {
+            return true;
+        }
+    }
+    match member.permissions(ctx).await {
+        Ok(p) => p.manage_guild(),
+        Err(_) => false
+    }
+}
+
+/// Whether the author of a message moderates the guild it was sent in.
+/// Must not be called while holding a lock on `ctx.data`.
+pub async fn author_is_moderator(ctx: &Context, msg: &Message) -> bool {
+    match msg.guild_id {
+        Some(gid) => is_moderator(ctx, gid, msg.author.id).await,
+        None => false
+    }
+}
+
+#[check]
+#[name = "Moderator"]
+async fn moderator_check(ctx: &Context, msg: &Message, _: &mut Args, _: &CommandOptions) -> Result<(), Reason> {
+    if author_is_moderator(ctx, msg).await {
+        Ok(())
+    } else {
+        Err(Reason::User("Only moderators can use this command.".to_string()))
+    }
+}
+
+/// Lets users know why a command they are not allowed to use was ignored.
+#[hook]
+pub async fn dispatch_error(ctx: &Context, msg: &Message, error: DispatchError) {
+    let response = match error {
+        DispatchError::CheckFailed(_, Reason::User(reason)) => reason,
+        DispatchError::LackingPermissions(_) => "You lack the permissions to use this command.".to_string(),
+        DispatchError::OnlyForGuilds => "This command can only be used in a server.".to_string(),
+        _ => return
+    };
+    let _ = msg.reply(ctx, response).await;
+}
@@ -1,187 +1,525 @@
-use std::fs;
-use std::cmp::PartialEq;
-use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
-use serde::ser::{Serialize as SerSerialize, SerializeTupleVariant, Serializer};
 
 use serenity::prelude::*;
-use serenity::prelude::SerenityError;
 use serenity::client::Context;
 use serenity::model::id::GuildId;
-use serenity::model::channel::{ ChannelType,  MessageType};
-use serenity::utils::EmbedMessageBuilding;
 use serenity::{
-    async_trait,
-    client::bridge::gateway::{GatewayIntents, ShardId, ShardManager},
     framework::standard::{
-        buckets::{LimitedFor, RevertBucket},
-        help_commands,
-        macros::{check, command, group, help, hook},
+        macros::{command, group},
         Args,
-        CommandGroup,
-        CommandOptions,
         CommandResult,
-        DispatchError,
-        HelpOptions,
-        Reason,
-        StandardFramework,
     },
-    http::Http,
     model::{
-        channel::{Channel, Message},
-        gateway::Ready,
+        channel::Message,
         id::UserId,
-        permissions::Permissions,
     },
-    utils::{
-        content_safe, ContentSafeOptions,
-        MessageBuilder
-    }
-};
-use rsteam::{
-    SteamID,
-    player_service::{OwnedGames, OwnedGame}
+    utils::MessageBuilder
 };
+use rsteam::SteamID;
+use chrono_tz::Tz;
 
+use game_randomizer::availability::Availability;
+use game_randomizer::endpoints::{gog, itch, steam, GameSources};
+use game_randomizer::endpoints::steam::Visibility;
+use game_randomizer::players::{common_games, gather_library, line_blocks, Account, Library, PlayerRegistry};
+use game_randomizer::titles::TitleMatcher;
 
-use crate::endpoints::steam;
-
-#[derive(Serialize,Deserialize)]
-pub enum Player {
-    Steam(UserId, u64),
-}
-impl Player {
-    pub fn discord(&self) -> &UserId {
-        match self {
-            Player::Steam(user, _) => &user
-        }
-    }
-    pub fn steam(&self) -> SteamID {
-        match self {
-            Player::Steam(_, user) => {
-                let id:SteamID = (*user).into();
-                id
-            }
-        }
-    }
-}
+use crate::commands::settings::{guild_settings, STEAM_CHECK};
+use crate::commands::components;
+use crate::commands::permissions::MODERATOR_CHECK;
 
+/// Largest library export accepted by the import command, in bytes.
+const MAX_IMPORT_SIZE: u64 = 5 * 1024 * 1024;
+/// How to get a GOG Galaxy library in a form the import command reads.
+const GOG_EXPORT_HELP: &str = "For GOG Galaxy, export your library with https://github.com/AB1908/GOG-Galaxy-Export-Script and attach its gameDetails.csv.";
 
 #[derive(Deserialize,Serialize)]
 pub struct PlayerContainer;
 impl TypeMapKey for PlayerContainer {
-    type Value = HashMap<GuildId, Vec<Player>>;
+    type Value = PlayerRegistry;
+}
+
+async fn link_account(ctx: &Context, user: UserId, account: Account) -> bool {
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+    winner.link(user, account)
 }
-impl PlayerContainer {
-    pub fn new() -> HashMap<GuildId, Vec<Player>> {
-        HashMap::new()
+
+/// Hides or shows a user's profile in a guild, returning false if they
+/// have no profile.
+async fn set_hidden(ctx: &Context, gid: GuildId, user: UserId, hidden: bool) -> bool {
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+    match winner.get_mut(user) {
+        Some(profile) => {
+            if hidden {
+                profile.hidden.insert(gid);
+            } else {
+                profile.hidden.remove(&gid);
+            }
+            true
+        },
+        None => false
     }
 }
 
+/// The zone a user's times are read and shown in, UTC unless they set one.
+pub async fn zone_of(ctx: &Context, user: UserId) -> Tz {
+    let rlock = ctx.data.read().await;
+    let players = rlock.get::<PlayerContainer>().expect("no player container found");
+    players.get(user).map(|p| p.zone()).unwrap_or(Tz::UTC)
+}
+
 #[group]
-#[commands(add_steam_id, find_common_games)]
+#[commands(add_steam_id, add_account, unlink, own, disown, import, timezone, available, whoami, linked, hide_profile, show_profile, remove_player, find_common_games)]
 pub struct Players;
 
 #[command]
+#[checks(Steam)]
 async fn add_steam_id(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     // https://www.ubisoft.com/en-gb/help/article/finding-your-steam-id/000060565
-    let gid = match msg.guild_id {
-        Some(g) => g,
-        None => {
-            msg.reply(ctx, "Cannot add players to non-guild channel.").await?;
-            return Ok(()); // todo error
+    let user = match args.single_quoted::<String>() {
+        Ok(u) => u,
+        Err(_) => {
+            msg.reply(ctx, "Please provide a steam id, profile link or vanity name.").await?;
+            return Ok(());
+        },
+    };
+    match link_steam(ctx, msg.author.id, &user).await {
+        Ok(response) | Err(response) => msg.reply(ctx, response).await?,
+    };
+    Ok(())
+}
+
+/// Links a steam id, profile link or vanity name to a user, returning what
+/// to tell them.
+pub async fn link_steam(ctx: &Context, user: UserId, input: &str) -> Result<String, String> {
+    let sid = resolve_steam(ctx, input).await?;
+    let id:u64 = (&sid).into();
+    if !link_account(ctx, user, Account::new("steam", &id.to_string())).await {
+        return Err("That steam id is already linked to your user.".to_string());
+    }
+    match privacy_warning(ctx, sid).await {
+        Some(warning) => Ok(format!{"Linked steam id to your user, but {}", warning}),
+        None => Ok("Linked steam id to your user.".to_string()),
+    }
+}
+
+/// Finds the steam id of a steam id, profile link or vanity name.
+async fn resolve_steam(ctx: &Context, input: &str) -> Result<SteamID, String> {
+    match input.parse::<steam::UserRef>()? {
+        steam::UserRef::Id(id) => Ok(id),
+        steam::UserRef::Vanity(name) => {
+            let rlock = ctx.data.read().await;
+            let steam_inner = rlock.get::<steam::Client>().expect("no steam client found");
+            let u = (**steam_inner).lock().await.resolve_vanity_user(&name).await;
+            u.map_err(|_| format!{"No steam user with the vanity name '{}'.", name})
+        },
+    }
+}
+
+/// Explains why a steam account's games can't be read, if they can't.
+async fn privacy_warning(ctx: &Context, sid: SteamID) -> Option<String> {
+    let rlock = ctx.data.read().await;
+    let steam_inner = rlock.get::<steam::Client>().expect("no steam client found");
+    let steam_lock = (**steam_inner).lock().await;
+    match steam_lock.player_summaries(&[sid]).await {
+        Ok(summaries) => match summaries.first() {
+            Some(s) if s.visibility != Visibility::Public => {
+                return Some(format!{"the profile is {:?}, make it public so common games can be found.", s.visibility});
+            },
+            Some(_) => {},
+            None => return Some("no steam profile was found for it.".to_string())
+        },
+        Err(_) => return None
+    };
+    match steam_lock.user_owned_games(&sid).await {
+        Ok(g) if !g.is_empty() => None,
+        _ => Some("its game details seem to be private, make them public so common games can be found.".to_string())
+    }
+}
+
+// ~add_account origin "some name"
+#[command]
+async fn add_account(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (platform, name) = match (args.single::<String>(), args.single_quoted::<String>()) {
+        (Ok(p), Ok(n)) if !n.trim().is_empty() => (p.to_ascii_lowercase(), n.trim().to_string()),
+        _ => {
+            msg.reply(ctx, "Please provide a platform and account name, ex: add_account origin \"name\"").await?;
+            return Ok(());
         }
     };
-    let sid: SteamID = match args.single_quoted::<String>() {
-        Ok(id) => match id.parse::<u64>() {
-            Ok(i) => i.into(),
-            Err(_) => {
-                let rlock = ctx.data.read().await;
-                let steam_inner = rlock.get::<steam::Client>().expect("no steam client found");
-                let u = match (**steam_inner).lock().await.resolve_vanity_user(&id).await {// is string
-                    Ok(u) => u,
-                    Err(_) => {
-                        msg.reply(ctx, "Invalid steam id and vanity user provided.").await?;
+    if platform == "steam" {
+        msg.reply(ctx, "Use add_steam_id to link steam accounts.").await?;
+        return Ok(());
+    }
+    let account = Account::new(&platform, &name);
+    if link_account(ctx, msg.author.id, account.clone()).await {
+        msg.reply(ctx, format!{"Linked {} to your user.", account}).await?;
+    } else {
+        msg.reply(ctx, "That account is already linked to your user.").await?;
+    }
+    Ok(())
+}
+
+// ~unlink, ~unlink STEAM_0:0:11101, ~unlink steam vanityname or ~unlink origin "name"
+#[command]
+async fn unlink(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let account = if args.is_empty() {
+        None
+    } else {
+        let first = args.single_quoted::<String>().unwrap_or_default();
+        match (first.to_ascii_lowercase().as_str(), first.parse::<steam::UserRef>()) {
+            (_, Ok(steam::UserRef::Id(id))) => Some(Account::new("steam", &id.to_string())),
+            ("steam", _) => {
+                let input = args.single_quoted::<String>().unwrap_or_default();
+                if input.trim().is_empty() {
+                    msg.reply(ctx, "Please provide the steam id to unlink.").await?;
+                    return Ok(());
+                }
+                match resolve_steam(ctx, &input).await {
+                    Ok(sid) => {
+                        let id:u64 = (&sid).into();
+                        Some(Account::new("steam", &id.to_string()))
+                    },
+                    Err(why) => {
+                        msg.reply(ctx, why).await?;
                         return Ok(());
                     }
-                };
-                u
+                }
+            },
+            (platform, _) => match args.single_quoted::<String>() {
+                Ok(name) => Some(Account::new(platform, name.trim())),
+                Err(_) => {
+                    msg.reply(ctx, "Please provide a steam id, or a platform and account name.").await?;
+                    return Ok(());
+                }
             }
+        }
+    };
+    let removed = {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+        winner.unlink(msg.author.id, account.as_ref())
+    };
+    match (removed, account) {
+        (true, Some(a)) => msg.reply(ctx, format!{"Unlinked {}.", a}).await?,
+        (true, None) => msg.reply(ctx, "Unlinked all of your accounts.").await?,
+        (false, Some(_)) => msg.reply(ctx, "That account is not linked to your user.").await?,
+        (false, None) => msg.reply(ctx, "You have no linked accounts.").await?,
+    };
+    Ok(())
+}
+
+// ~own "Rocket League", for games on platforms without a library api
+#[command]
+async fn own(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let title = args.rest().trim().trim_matches('"').trim().to_string();
+    if title.is_empty() {
+        msg.reply(ctx, "Please provide the title of a game you own.").await?;
+        return Ok(());
+    }
+    let added = {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+        winner.own(msg.author.id, &title)
+    };
+    if added {
+        msg.reply(ctx, format!{"Added {} to your games.", title}).await?;
+    } else {
+        msg.reply(ctx, "You already own that game.").await?;
+    }
+    Ok(())
+}
+
+// ~disown "Rocket League"
+#[command]
+async fn disown(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let title = args.rest().trim().trim_matches('"').trim().to_string();
+    if title.is_empty() {
+        msg.reply(ctx, "Please provide the title of a game to remove.").await?;
+        return Ok(());
+    }
+    let removed = {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+        winner.disown(msg.author.id, &title)
+    };
+    if removed {
+        msg.reply(ctx, format!{"Removed {} from your games.", title}).await?;
+    } else {
+        msg.reply(ctx, "That game is not in your games.").await?;
+    }
+    Ok(())
+}
+
+// ~import [gog|itch] with a GOG Galaxy CSV export or itch.io library JSON attached
+#[command]
+async fn import(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let attachment = match msg.attachments.first() {
+        Some(a) => a,
+        None => {
+            msg.reply(ctx, format!{"Please attach a GOG Galaxy CSV export or an itch.io library JSON file. {}", GOG_EXPORT_HELP}).await?;
+            return Ok(());
+        }
+    };
+    if attachment.size > MAX_IMPORT_SIZE {
+        msg.reply(ctx, "That file is too large to import.").await?;
+        return Ok(());
+    }
+    let filename = attachment.filename.to_ascii_lowercase();
+    let platform = match args.single::<String>().map(|p| p.to_ascii_lowercase()) {
+        Ok(p) if p == "gog" || p == "itch" => p,
+        Ok(_) => {
+            msg.reply(ctx, "Imports are supported for 'gog' and 'itch'.").await?;
+            return Ok(());
+        },
+        Err(_) if filename.ends_with(".csv") => "gog".to_string(),
+        Err(_) if filename.ends_with(".json") => "itch".to_string(),
+        Err(_) if filename.ends_with(".db") => {
+            msg.reply(ctx, format!{"GOG Galaxy databases can't be read directly. {}", GOG_EXPORT_HELP}).await?;
+            return Ok(());
         },
         Err(_) => {
-            msg.reply(ctx, "Please provide a steam id or user.").await?;
+            msg.reply(ctx, "Could not tell what the file is, add 'gog' or 'itch' to the command.").await?;
+            return Ok(());
+        }
+    };
+    let data = match attachment.download().await.map(String::from_utf8) {
+        Ok(Ok(d)) => d,
+        _ => {
+            msg.reply(ctx, "Could not read the attached file.").await?;
+            return Ok(());
+        }
+    };
+    let parsed = match platform.as_str() {
+        "gog" => gog::parse_export(&data),
+        _ => itch::parse_library(&data),
+    };
+    let titles = match parsed {
+        Ok(t) if !t.is_empty() => t,
+        Ok(_) => {
+            msg.reply(ctx, "No games were found in that file.").await?;
             return Ok(());
         },
+        Err(why) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
     };
-    let id:u64 = (&sid).into();
+    let count = titles.len();
     {
         let mut wlock = ctx.data.write().await;
-        let mut winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
-        if let Some(existing) = winner.get_mut(&gid) {
-            for player in existing.iter_mut() {
-                if *player.discord() == msg.author.id {
-                    if player.steam() == sid {
-                        msg.reply(ctx, "Your discord and steam users match.").await?;
-                    } else {
-                        *player = Player::Steam(msg.author.id, id);
-                        msg.reply(ctx, "Updated your steam id.").await?;
-                    }
-                    return Ok(());
-                }
+        let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+        winner.import(msg.author.id, &platform, titles);
+    }
+    msg.reply(ctx, format!{"Imported {} {} games, replacing any earlier import.", count, platform}).await?;
+    Ok(())
+}
+
+// ~timezone Europe/Berlin, ~timezone clear or ~timezone to show it
+#[command]
+async fn timezone(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let zone = match args.single::<String>() {
+        Ok(z) if z.eq_ignore_ascii_case("clear") => None,
+        Ok(z) => match z.parse::<Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                msg.reply(ctx, format!{"Unknown time zone '{}', use a name like Europe/Berlin or America/New_York.", z}).await?;
+                return Ok(());
             }
-            // no matches
-            existing.push(Player::Steam(msg.author.id, id));
-            msg.reply(ctx, "Added steam id to your user.").await?;
-        } else { // no gid or user
-            winner.insert(gid, vec![ Player::Steam(msg.author.id, id) ]);
-            msg.reply(ctx, "Added steam id to your user.").await?;
+        },
+        Err(_) => {
+            let zone = zone_of(ctx, msg.author.id).await;
+            msg.reply(ctx, format!{"Your time zone is {}, change it with: timezone Europe/Berlin", zone.name()}).await?;
+            return Ok(());
         }
+    };
+    {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+        winner.set_timezone(msg.author.id, zone);
     }
+    let response = match zone {
+        Some(z) => format!{"Your time zone is now {}.", z.name()},
+        None => "Cleared your time zone, times are read in UTC.".to_string()
+    };
+    msg.reply(ctx, response).await?;
     Ok(())
 }
 
+// ~available fri 19:00-23:00, sat 14:00-02:00, ~available clear or ~available to show it
+#[command]
+async fn available(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let input = args.rest().trim();
+    if input.is_empty() {
+        let (zone, availability) = {
+            let rlock = ctx.data.read().await;
+            let players = rlock.get::<PlayerContainer>().expect("no player container found");
+            match players.get(msg.author.id) {
+                Some(p) => (p.zone(), p.availability.clone()),
+                None => (Tz::UTC, Vec::new())
+            }
+        };
+        let response = if availability.is_empty() {
+            "You haven't said when you can play, ex: available fri 19:00-23:00, sat 14:00-18:00".to_string()
+        } else {
+            let times: Vec<String> = availability.iter().map(|a| a.to_string()).collect();
+            format!{"You can usually play {} ({}).", times.join(", "), zone.name()}
+        };
+        msg.reply(ctx, response).await?;
+        return Ok(());
+    }
+    let availability = if input.eq_ignore_ascii_case("clear") {
+        Vec::new()
+    } else {
+        match input.split(',').map(|a| a.parse::<Availability>()).collect::<Result<Vec<_>, _>>() {
+            Ok(a) => a,
+            Err(why) => {
+                msg.reply(ctx, why).await?;
+                return Ok(());
+            }
+        }
+    };
+    let zone = zone_of(ctx, msg.author.id).await;
+    let response = if availability.is_empty() {
+        "Cleared when you can play.".to_string()
+    } else {
+        format!{"Saved {} weekly time(s) in {}, set your zone with timezone if that's wrong.", availability.len(), zone.name()}
+    };
+    {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<PlayerContainer>().expect("no player data stored");
+        winner.set_availability(msg.author.id, availability);
+    }
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
 
-fn owned_game_eq(this:&OwnedGame, other: &OwnedGame) -> bool {
-    this.appid == other.appid
+// ~whoami
+#[command]
+async fn whoami(ctx: &Context, msg: &Message) -> CommandResult {
+    let (accounts, library, timezone) = {
+        let rlock = ctx.data.read().await;
+        let players = rlock.get::<PlayerContainer>().expect("no player container found");
+        match players.get(msg.author.id) {
+            Some(p) => (p.accounts.clone(), p.manual_titles().count(), p.timezone),
+            None => (Vec::new(), 0, None)
+        }
+    };
+    if accounts.is_empty() && library == 0 {
+        msg.reply(ctx, "You have no linked accounts, link one with add_steam_id.").await?;
+        return Ok(());
+    }
+    let ids: Vec<SteamID> = accounts.iter().filter_map(|a| a.steam()).collect();
+    let summaries = if ids.is_empty() {
+        Vec::new()
+    } else {
+        let rlock = ctx.data.read().await;
+        let steam_inner = rlock.get::<steam::Client>().expect("no steam client found");
+        let steam_lock = (**steam_inner).lock().await;
+        steam_lock.player_summaries(&ids).await.unwrap_or_default()
+    };
+    let mut response = MessageBuilder::new();
+    response.push_line("Your linked accounts:");
+    for account in accounts.iter() {
+        let summary = account.steam().and_then(|id| summaries.iter().find(|s| s.id == id));
+        match summary {
+            Some(s) => response.push_line(format!{"steam: {} ({:?}) <{}>", s.name, s.visibility, s.url}),
+            None => response.push_line(account.to_string()),
+        };
+    }
+    if library > 0 {
+        response.push_line(format!{"Plus {} game(s) you own elsewhere.", library});
+    }
+    if let Some(zone) = timezone {
+        response.push_line(format!{"Time zone: {}", zone.name()});
+    }
+    msg.reply(ctx, response.build()).await?;
+    Ok(())
 }
-fn find_common(left: &OwnedGames, right: &OwnedGames) -> OwnedGames {
-    let mut mutual = OwnedGames{
-        game_count: 0,
-        games: Vec::new()
+
+// ~linked, members of this server with linked accounts
+#[command]
+#[only_in(guilds)]
+async fn linked(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let profiles: Vec<(UserId, usize)> = {
+        let rlock = ctx.data.read().await;
+        let players = rlock.get::<PlayerContainer>().expect("no player container found");
+        players.iter()
+            .filter(|p| p.visible_in(gid))
+            .map(|p| (p.user, p.accounts.len()))
+            .collect()
     };
-    for lgame in left.games.iter() {
-        //if right.games.contains(lgame) {
-        if right.games.iter().any(|g| owned_game_eq(g, lgame)) {
-            let name = match lgame.name.as_deref() {
-                Some(n) => Some(n.to_owned()),
-                None => None
-            };
-            let img_icon_url = match lgame.img_icon_url.as_deref() {
-                Some(i) => Some(i.to_owned()),
-                None => None
-            };
-            let img_logo_url = match lgame.img_logo_url.as_deref() {
-                Some(i) => Some(i.to_owned()),
-                None => None
-            };
-            mutual.games.push(
-                OwnedGame {
-                    appid: lgame.appid,
-                    name: name,
-                    playtime_forever: lgame.playtime_forever,
-                    img_icon_url: img_icon_url,
-                    img_logo_url: img_logo_url,
-                    playtime_windows_forever: lgame.playtime_windows_forever,
-                    playtime_mac_forever: lgame.playtime_mac_forever,
-                    playtime_linux_forever: lgame.playtime_linux_forever
-                }
-            );
+    let mut lines = Vec::new();
+    for (user, count) in profiles {
+        if let Some(member) = ctx.cache.member(gid, user).await {
+            lines.push(format!{"{} - {} account(s)", member.display_name(), count});
         }
     }
-    mutual.game_count = mutual.games.len() as u32;
-    mutual
+    if lines.is_empty() {
+        msg.reply(ctx, "Nobody on this server has linked accounts.").await?;
+        return Ok(());
+    }
+    lines.sort();
+    let response = MessageBuilder::new()
+        .push_line("Linked players")
+        .push_codeblock_safe(lines.join("\n"), None)
+        .build();
+    msg.reply(ctx, response).await?;
+    Ok(())
 }
+
+// ~hide_profile, keeps your linked accounts out of this server
 #[command]
+#[only_in(guilds)]
+async fn hide_profile(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    if set_hidden(ctx, gid, msg.author.id, true).await {
+        msg.reply(ctx, "Your linked accounts are now hidden on this server.").await?;
+    } else {
+        msg.reply(ctx, "You have no linked accounts.").await?;
+    }
+    Ok(())
+}
+
+// ~show_profile
+#[command]
+#[only_in(guilds)]
+async fn show_profile(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    if set_hidden(ctx, gid, msg.author.id, false).await {
+        msg.reply(ctx, "Your linked accounts are now visible on this server.").await?;
+    } else {
+        msg.reply(ctx, "You have no linked accounts.").await?;
+    }
+    Ok(())
+}
+
+// ~remove_player @user, hides their linked accounts on this server
+#[command]
+#[only_in(guilds)]
+#[checks(Moderator)]
+async fn remove_player(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let user = match args.single::<UserId>() {
+        Ok(u) => u,
+        Err(_) => {
+            msg.reply(ctx, "Please mention the user to remove.").await?;
+            return Ok(());
+        }
+    };
+    if set_hidden(ctx, gid, user, true).await {
+        msg.reply(ctx, "Removed the user's linked accounts from this server.").await?;
+    } else {
+        msg.reply(ctx, "That user has no linked accounts.").await?;
+    }
+    Ok(())
+}
+
+#[command]
+#[checks(Steam)]
 async fn find_common_games(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let gid = match msg.guild_id {
         Some(g) => g,
@@ -209,7 +547,6 @@ async fn find_common_games(ctx: &Context, msg: &Message, mut args: Args) -> Comm
                 if *nick == user {
                     users.push(g_user.user.id);
                     break;
-                } else {
                 }
             }
             // didnt match or no nick
@@ -223,100 +560,96 @@ async fn find_common_games(ctx: &Context, msg: &Message, mut args: Args) -> Comm
         msg.reply(ctx, "Not enough discord names found to find common games.").await?;
         return Ok(());
     }
-    let mut ids: Vec<SteamID> = Vec::new();
-    // match discord names and store associated steam id
+    let found = match find_common(ctx, gid, &users).await {
+        Ok(f) => f,
+        Err(why) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
+    };
+    let mut responses = found.responses.into_iter().peekable();
+    while let Some(response) = responses.next() {
+        if responses.peek().is_some() || found.titles.len() < 2 {
+            msg.reply(ctx, response).await?;
+            continue;
+        }
+        // the last message lets players start a poll from the results
+        msg.channel_id.send_message(ctx, |m| m
+            .reference_message(msg)
+            .content(response)
+            .components(|c| components::poll_menu(c, &found.titles))
+        ).await?;
+    }
+    Ok(())
+}
+
+/// Common games found for some players.
+pub struct CommonGames {
+    /// Messages to post in order: accounts that couldn't be read, then the
+    /// games in code blocks that fit discord's length limit.
+    pub responses: Vec<String>,
+    pub titles: Vec<String>,
+}
+
+/// Finds the games all of the users' linked accounts and manual libraries
+/// have in common.
+pub async fn find_common(ctx: &Context, gid: GuildId, users: &[UserId]) -> Result<CommonGames, String> {
+    let mut players: Vec<(Vec<Account>, Vec<String>)> = Vec::new();
+    // store each user's accounts with a game source and their manual library
     { // playercontainer read lock
         let rlock = ctx.data.read().await;
-        let hash = rlock.get::<PlayerContainer>().expect("no player container found");
-        let players = match hash.get(&gid) {
-            Some(p) => p,
-            None => {
-                msg.reply(ctx, "No player to steam mappings found for guild.").await?;
-                return Ok(());
-            }
-        };
-
+        let registry = rlock.get::<PlayerContainer>().expect("no player container found");
         for d_user in users.iter() {
-            for player in players {
-                if player.discord() == d_user {
-                    ids.push(player.steam());
+            if let Some(player) = registry.in_guild(gid, *d_user) {
+                let sourced: Vec<Account> = player.accounts.iter()
+                    .filter(|a| a.source().is_some())
+                    .cloned()
+                    .collect();
+                let manual: Vec<String> = player.manual_titles().cloned().collect();
+                if !sourced.is_empty() || !manual.is_empty() {
+                    players.push((sourced, manual));
                 }
             }
         }
     }
-    if ids.len() < 2 {
-        msg.reply(ctx, "Not enough names match discord users to find common games.").await?;
-        return Ok(());
+    if players.len() < 2 {
+        return Err("Not enough names match discord users to find common games.".to_string());
     }
-    let mut owned_games: Vec<OwnedGames> = Vec::new();
-    // get all steam games for each steam id
-    { // steam read lock
+    let sources = {
         let rlock = ctx.data.read().await;
-        let steam_inner = rlock.get::<steam::Client>().expect("no steam client found");
-        for id in ids.iter() {
-            match (**steam_inner).lock().await.user_owned_games(&id).await {
-                Ok(g) => owned_games.push(g),
-                Err(_) => {
-                    msg.reply(ctx, format!{"Could not find games for: {}", id}).await?;
-                    //return Ok(());
-                }
-            };
+        rlock.get::<GameSources>().expect("no game sources").clone()
+    };
+    let aliases = guild_settings(ctx, Some(gid)).await.aliases;
+    let matcher = TitleMatcher::new(&aliases);
+    let mut responses = Vec::new();
+    let mut owned_games: Vec<Library> = Vec::new();
+    // get all games for each account, merged per user
+    for (accounts, manual) in players {
+        let (library, failed) = gather_library(&accounts, &manual, &sources, &matcher).await;
+        for account in failed {
+            responses.push(format!{"Could not find games for: {}", account});
+        }
+        if !library.is_empty() {
+            owned_games.push(library);
         }
     }
     // find common across all games
-    let common = if let Some(first) = owned_games.pop() { // grab a users games as acc
-        owned_games.iter().fold(first, |acc, right| find_common(&acc, right))
-    } else {
-        msg.reply(ctx, "Failure processing common games.").await?;
-        return Ok(());
-    };
-    if common.game_count == 0 || common.games.is_empty() {
-        msg.reply(ctx, "There are no shared games between requested players.").await?;
-        return Ok(());
+    let common = common_games(owned_games);
+    if common.is_empty() {
+        responses.push("There are no shared games between requested players.".to_string());
+        return Ok(CommonGames { responses, titles: Vec::new() });
     }
     //convert to names - link
-    let mut games = Vec::new();
-    { // steam read lock
-        let rlock = ctx.data.read().await;
-        let steam_inner = rlock.get::<steam::Client>().expect("no steam client found");
-        let steam_lock = (**steam_inner).lock().await;
-
-        for game in common.games.iter() {
-            let name = match &game.name {
-                Some(n) => n.clone(),
-                None => {// appid to name
-                    match steam_lock.game_by_id(game.appid).await {
-                        Ok(app) => app.name,
-                        Err(()) => String::from("** NNF **"),
-                    }
-                }
-            };
-            games.push(format!{"{} - https://store.steampowered.com/app/{}/\r\n", name, game.appid});
-        }
-    }
+    let games: Vec<String> = common.iter()
+        .map(|game| format!{"{}\r\n", game})
+        .collect();
     // separate code blocks into <2k messages
-    let mut game_block = String::new();
-    let mut count = 0;
-    for game in games.iter() {
-        if game_block.len() + game.len() <= 1950 {
-            game_block.push_str(game);
-        } else {
-            let response = MessageBuilder::new()
-                .push_line(&format!{"Common games {}", count})
-                .push_codeblock_safe(game_block, None)
-                .build();
-            msg.reply(ctx, response).await?;
-            game_block = String::new();
-            count += 1;
-        }
+    for (count, block) in line_blocks(&games, 1950).iter().enumerate() {
+        responses.push(MessageBuilder::new()
+            .push_line(format!{"Common games {}", count})
+            .push_codeblock_safe(block, None)
+            .build());
     }
-    if game_block.len() != 0 {
-        let response = MessageBuilder::new()
-            .push_line(&format!{"Common games {}", count})
-            .push_codeblock_safe(game_block, None)
-            .build();
-        msg.reply(ctx, response).await?;
-        game_block = String::new();
-    }
-    Ok(())
+    let titles = common.iter().map(|g| g.title().to_string()).collect();
+    Ok(CommonGames { responses, titles })
 }
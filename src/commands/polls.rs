@@ -4,11 +4,26 @@ use tokio::sync::Mutex;
 
 use serenity::{
     prelude::*,
+    client::Context,
+    framework::standard::{
+        macros::{command, group},
+        Args,
+        CommandResult
+    },
     model::{
+        channel::{Message, ReactionType},
         id::{ChannelId, MessageId}
-    }
+    },
+    utils::MessageBuilder
 };
 
+use game_randomizer::polls::{StrawPoll, MAX_ANSWERS};
+
+use crate::commands::permissions::author_is_moderator;
+
+/// Reactions used to vote for each answer, in order.
+pub const ANSWER_EMOJI: [&str; MAX_ANSWERS] = ["1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣", "6️⃣", "7️⃣", "8️⃣", "9️⃣", "🔟"];
+
 pub struct StrawPollKey;
 
 impl TypeMapKey for StrawPollKey {
@@ -16,23 +31,104 @@ impl TypeMapKey for StrawPollKey {
 }
 
 pub type StrawPollMap = HashMap<(ChannelId, MessageId), StrawPoll>;
-pub struct StrawPoll {
-  pub question: String,
-  pub answers: Vec<String>,
-  pub answerers: Vec<usize>,
+
+#[group]
+#[only_in(guilds)]
+#[commands(poll, end_poll)]
+pub struct Polls;
+
+// ~poll "What tonight?" "Deep Rock" "Valheim"
+#[command]
+async fn poll(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut poll = match args.single_quoted::<String>() {
+        Ok(q) => StrawPoll::new(msg.author.id, q),
+        Err(_) => {
+            msg.reply(ctx, "Please provide a question and at least two answers.").await?;
+            return Ok(());
+        }
+    };
+    for answer in args.quoted().iter::<String>().flatten() {
+        if let Err(why) = poll.add_answer(answer) {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
+    }
+    if !poll.is_ready() {
+        msg.reply(ctx, format!{"Polls need between 2 and {} answers.", MAX_ANSWERS}).await?;
+        return Ok(());
+    }
+    let posted = msg.channel_id.say(ctx, render(&poll)).await?;
+    open(ctx, &posted, poll).await
 }
-impl StrawPoll {
-    pub fn set_question<Q>(&mut self, question: Q) where Q: Into<String> {
-        self.question = question.into();
+
+/// The question and numbered answers of a poll.
+pub fn render(poll: &StrawPoll) -> String {
+    let mut content = MessageBuilder::new();
+    content.push_bold_line_safe(&poll.question);
+    for (emoji, answer) in ANSWER_EMOJI.iter().zip(poll.answers.iter()) {
+        content.push(emoji).push(" ").push_line_safe(answer);
     }
-    pub fn add_answer<A>(&mut self, answer: A) where A: Into<String> {
-        self.answers.push( answer.into() );
+    content.build()
+}
+
+/// Adds the answer reactions to a posted poll and keeps it open for votes.
+pub async fn open(ctx: &Context, posted: &Message, poll: StrawPoll) -> CommandResult {
+    for emoji in ANSWER_EMOJI.iter().take(poll.answers.len()) {
+        posted.react(ctx, ReactionType::Unicode(emoji.to_string())).await?;
     }
-    pub fn remove_answer(&mut self, answer: usize) -> Option<()> {
-        if self.answers.len() <= answer  {
-            return None;
+    let polls = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<StrawPollKey>().expect("no polls stored").clone()
+    };
+    polls.lock().await.insert((posted.channel_id, posted.id), poll);
+    Ok(())
+}
+
+// ~end_poll [message id], defaults to the latest poll in the channel
+#[command]
+async fn end_poll(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let moderator = author_is_moderator(ctx, msg).await;
+    let polls = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<StrawPollKey>().expect("no polls stored").clone()
+    };
+    let mut polls = polls.lock().await;
+    let key = match args.single::<u64>() {
+        Ok(id) => (msg.channel_id, MessageId(id)),
+        Err(_) => match polls.keys().filter(|(c, _)| *c == msg.channel_id).max_by_key(|(_, m)| *m) {
+            Some(k) => *k,
+            None => {
+                msg.reply(ctx, "There are no open polls in this channel.").await?;
+                return Ok(());
+            }
+        }
+    };
+    match polls.get(&key) {
+        Some(p) if p.creator == msg.author.id || moderator => {},
+        Some(_) => {
+            msg.reply(ctx, "Only the poll creator or a moderator can end this poll.").await?;
+            return Ok(());
+        },
+        None => {
+            msg.reply(ctx, "No open poll with that id in this channel.").await?;
+            return Ok(());
         }
-        self.answers.remove(answer);
-        Some( () )
+    };
+    let mut poll = polls.remove(&key).expect("poll checked above");
+    let posted = key.0.message(ctx, key.1).await?;
+    // the bot's own reaction is included in every count
+    let counts: Vec<usize> = ANSWER_EMOJI.iter().take(poll.answers.len())
+        .map(|emoji| posted.reactions.iter()
+            .find(|r| r.reaction_type == ReactionType::Unicode(emoji.to_string()))
+            .map(|r| (r.count as usize).saturating_sub(1))
+            .unwrap_or(0))
+        .collect();
+    poll.close(&counts);
+    let mut response = MessageBuilder::new();
+    response.push("Results for: ").push_bold_line_safe(&poll.question);
+    for (answer, votes) in poll.results() {
+        response.push_safe(answer).push_line(format!{" - {} votes", votes});
     }
+    msg.channel_id.say(ctx, response.build()).await?;
+    Ok(())
 }
@@ -0,0 +1,419 @@
+use std::fmt;
+use std::collections::{BTreeMap, HashMap};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+use serenity::prelude::*;
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{check, command, group, hook},
+        Args,
+        CommandOptions,
+        CommandResult,
+        Reason
+    },
+    model::{
+        channel::Message,
+        id::{ChannelId, GuildId, RoleId, UserId},
+        misc::Mentionable,
+    },
+    utils::MessageBuilder
+};
+
+use game_randomizer::picker::PickerWeights;
+use game_randomizer::ratelimits::{Bucket, RateLimit, RateLimiter, RateLimits};
+
+pub const DEFAULT_PREFIX: &str = "~";
+
+/// What happens to the suggestions of members who leave the guild.
+#[derive(Clone,Copy,Debug,Default,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepartedPolicy {
+    #[default]
+    Keep,
+    Archive,
+    Transfer(UserId),
+}
+impl fmt::Display for DepartedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepartedPolicy::Keep => write!(f, "kept"),
+            DepartedPolicy::Archive => write!(f, "dropped"),
+            DepartedPolicy::Transfer(user) => write!(f, "transferred to {}", user.mention()),
+        }
+    }
+}
+
+#[derive(Clone,Debug,Deserialize,Serialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    pub prefix: String,
+    pub bot_channel: Option<ChannelId>,
+    pub moderator_role: Option<RoleId>,
+    pub weights: PickerWeights,
+    pub allow_plain: bool,
+    pub max_suggestions: Option<usize>,
+    pub rate_limits: bool,
+    pub limits: RateLimits,
+    pub departed: DepartedPolicy,
+    /// Normalized titles that mean another game when finding common games.
+    pub aliases: BTreeMap<String, String>,
+}
+impl Default for GuildSettings {
+    fn default() -> Self {
+        GuildSettings {
+            prefix: DEFAULT_PREFIX.to_string(),
+            bot_channel: None,
+            moderator_role: None,
+            weights: PickerWeights::default(),
+            allow_plain: true,
+            max_suggestions: None,
+            rate_limits: true,
+            limits: RateLimits::default(),
+            departed: DepartedPolicy::default(),
+            aliases: BTreeMap::new()
+        }
+    }
+}
+impl fmt::Display for GuildSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Prefix: {}", self.prefix)?;
+        match self.bot_channel {
+            Some(c) => writeln!(f, "Bot channel: {}", c.mention())?,
+            None => writeln!(f, "Bot channel: any")?,
+        };
+        match self.moderator_role {
+            Some(r) => writeln!(f, "Moderator role: {}", r.mention())?,
+            None => writeln!(f, "Moderator role: none")?,
+        };
+        writeln!(f, "Picker weights: {}", self.weights)?;
+        writeln!(f, "Plain suggestions allowed: {}", self.allow_plain)?;
+        match self.max_suggestions {
+            Some(m) => writeln!(f, "Max active suggestions per user: {}", m)?,
+            None => writeln!(f, "Max active suggestions per user: unlimited")?,
+        };
+        if self.rate_limits {
+            writeln!(f, "Rate limits: {}", self.limits)?;
+        } else {
+            writeln!(f, "Rate limits: off")?;
+        }
+        writeln!(f, "Suggestions of members who leave are {}", self.departed)?;
+        write!(f, "Title aliases: {}", self.aliases.len())
+    }
+}
+
+#[derive(Deserialize,Serialize)]
+pub struct GuildSettingsContainer;
+impl TypeMapKey for GuildSettingsContainer {
+    type Value = HashMap<GuildId, GuildSettings>;
+}
+
+/// Settings for the guild, or the defaults outside of guilds and for
+/// guilds that have not changed anything.
+pub async fn guild_settings(ctx: &Context, gid: Option<GuildId>) -> GuildSettings {
+    let gid = match gid {
+        Some(g) => g,
+        None => return GuildSettings::default()
+    };
+    let rlock = ctx.data.read().await;
+    let inner = rlock.get::<GuildSettingsContainer>().expect("no settings read data");
+    inner.get(&gid).cloned().unwrap_or_default()
+}
+
+/// Resolves the command prefix for the guild a message was sent in.
+pub async fn guild_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+    Some(guild_settings(ctx, msg.guild_id).await.prefix)
+}
+
+/// Ignores commands outside of a guild's bot channel, except for settings
+/// so that a bad channel can always be corrected.
+#[hook]
+pub async fn bot_channel_only(ctx: &Context, msg: &Message, command_name: &str) -> bool {
+    let is_settings = SETTINGS_GROUP.options.commands.iter()
+        .any(|c| c.options.names.contains(&command_name));
+    if is_settings {
+        return true;
+    }
+    match guild_settings(ctx, msg.guild_id).await.bot_channel {
+        Some(channel) => channel == msg.channel_id,
+        None => true
+    }
+}
+
+#[derive(Deserialize,Serialize)]
+pub struct RateLimiterContainer;
+impl TypeMapKey for RateLimiterContainer {
+    type Value = RateLimiter;
+}
+
+/// Counts a use of a rate limited command against the guild's limits, or
+/// says how long to wait. Guilds can turn the limits off, and commands
+/// outside of guilds are not limited.
+pub async fn rate_limit(ctx: &Context, gid: Option<GuildId>, user: UserId, bucket: Bucket) -> Result<(), String> {
+    let settings = match gid {
+        Some(_) => guild_settings(ctx, gid).await,
+        None => return Ok(())
+    };
+    if !settings.rate_limits {
+        return Ok(());
+    }
+    let limit = settings.limits.get(bucket);
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<RateLimiterContainer>().expect("no rate limiter stored");
+    winner.hit(gid.expect("checked above"), user, bucket, &limit, Utc::now())
+        .map_err(|wait| format!{"Slow down, try again in {} seconds.", wait.num_seconds().max(1)})
+}
+
+#[check]
+#[name = "Suggesting"]
+async fn suggesting_check(ctx: &Context, msg: &Message, _: &mut Args, _: &CommandOptions) -> Result<(), Reason> {
+    rate_limit(ctx, msg.guild_id, msg.author.id, Bucket::Suggest).await.map_err(Reason::User)
+}
+
+#[check]
+#[name = "Steam"]
+async fn steam_check(ctx: &Context, msg: &Message, _: &mut Args, _: &CommandOptions) -> Result<(), Reason> {
+    rate_limit(ctx, msg.guild_id, msg.author.id, Bucket::Steam).await.map_err(Reason::User)
+}
+
+fn parse_toggle(args: &mut Args) -> Option<bool> {
+    match args.single::<String>().map(|a| a.to_ascii_lowercase()) {
+        Ok(a) if a == "on" || a == "true" || a == "yes" => Some(true),
+        Ok(a) if a == "off" || a == "false" || a == "no" => Some(false),
+        _ => None
+    }
+}
+
+pub async fn update_settings<F>(ctx: &Context, gid: GuildId, update: F) -> GuildSettings
+where F: FnOnce(&mut GuildSettings) {
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<GuildSettingsContainer>().expect("no settings write data");
+    let settings = winner.entry(gid).or_insert_with(GuildSettings::default);
+    update(settings);
+    settings.clone()
+}
+
+#[group]
+#[prefixes("settings")]
+#[only_in(guilds)]
+#[default_command(show)]
+#[commands(show, prefix, channel, modrole, weights, plain, max_suggestions, rate_limits, departed)]
+pub struct Settings;
+
+#[command]
+async fn show(ctx: &Context, msg: &Message) -> CommandResult {
+    let settings = guild_settings(ctx, msg.guild_id).await;
+    let response = MessageBuilder::new()
+        .push_line("Settings for this server")
+        .push(settings.to_string())
+        .build();
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
+
+// ~settings prefix !
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn prefix(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let prefix = match args.single::<String>() {
+        Ok(p) if !p.trim().is_empty() => p.trim().to_string(),
+        _ => {
+            msg.reply(ctx, "Please provide a prefix.").await?;
+            return Ok(());
+        }
+    };
+    let settings = update_settings(ctx, gid, |s| s.prefix = prefix).await;
+    msg.reply(ctx, format!{"Command prefix is now: {}", settings.prefix}).await?;
+    Ok(())
+}
+
+// ~settings channel #games | none
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn channel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let channel = match args.single::<String>() {
+        Ok(c) if c.eq_ignore_ascii_case("none") => None,
+        Ok(c) => match c.parse::<ChannelId>() {
+            Ok(c) => Some(c),
+            Err(_) => {
+                msg.reply(ctx, "Invalid channel, mention one or use 'none'.").await?;
+                return Ok(());
+            }
+        },
+        Err(_) => {
+            msg.reply(ctx, "Please provide a channel or 'none'.").await?;
+            return Ok(());
+        }
+    };
+    update_settings(ctx, gid, |s| s.bot_channel = channel).await;
+    match channel {
+        Some(c) => msg.reply(ctx, format!{"Commands are now only accepted in {}", c.mention()}).await?,
+        None => msg.reply(ctx, "Commands are now accepted in any channel.").await?,
+    };
+    Ok(())
+}
+
+// ~settings modrole @Moderators | none
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn modrole(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let role = match args.single::<String>() {
+        Ok(r) if r.eq_ignore_ascii_case("none") => None,
+        Ok(r) => match r.parse::<RoleId>() {
+            Ok(r) => Some(r),
+            Err(_) => {
+                msg.reply(ctx, "Invalid role, mention one or use 'none'.").await?;
+                return Ok(());
+            }
+        },
+        Err(_) => {
+            msg.reply(ctx, "Please provide a role or 'none'.").await?;
+            return Ok(());
+        }
+    };
+    update_settings(ctx, gid, |s| s.moderator_role = role).await;
+    match role {
+        Some(r) => msg.reply(ctx, format!{"Moderator role is now {}", r.mention()}).await?,
+        None => msg.reply(ctx, "Moderator role removed.").await?,
+    };
+    Ok(())
+}
+
+// ~settings weights 2 1 [votes]
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn weights(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let weights = match (args.single::<u32>(), args.single::<u32>()) {
+        (Ok(store), Ok(plain)) => {
+            let votes = args.single::<u32>().unwrap_or_else(|_| PickerWeights::default().votes);
+            PickerWeights::new(store, plain, votes)
+        },
+        _ => Err("Please provide a store and plain weight and optionally a per vote weight, ex: weights 2 1 1".to_string())
+    };
+    let weights = match weights {
+        Ok(w) => w,
+        Err(why) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
+    };
+    let settings = update_settings(ctx, gid, |s| s.weights = weights).await;
+    msg.reply(ctx, format!{"Picker weights are now: {}", settings.weights}).await?;
+    Ok(())
+}
+
+// ~settings plain on | off
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn plain(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let allow = match parse_toggle(&mut args) {
+        Some(a) => a,
+        None => {
+            msg.reply(ctx, "Please provide 'on' or 'off'.").await?;
+            return Ok(());
+        }
+    };
+    update_settings(ctx, gid, |s| s.allow_plain = allow).await;
+    if allow {
+        msg.reply(ctx, "Plain text suggestions are now allowed.").await?;
+    } else {
+        msg.reply(ctx, "Plain text suggestions are now disabled.").await?;
+    }
+    Ok(())
+}
+
+// ~settings max_suggestions 5 | none
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn max_suggestions(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let max = match args.single::<String>() {
+        Ok(m) if m.eq_ignore_ascii_case("none") => None,
+        Ok(m) => match m.parse::<usize>() {
+            Ok(m) => Some(m),
+            Err(_) => {
+                msg.reply(ctx, "Invalid number, provide a count or 'none'.").await?;
+                return Ok(());
+            }
+        },
+        Err(_) => {
+            msg.reply(ctx, "Please provide a count or 'none'.").await?;
+            return Ok(());
+        }
+    };
+    update_settings(ctx, gid, |s| s.max_suggestions = max).await;
+    match max {
+        Some(m) => msg.reply(ctx, format!{"Users may now have {} active suggestions each.", m}).await?,
+        None => msg.reply(ctx, "Users may now add unlimited suggestions.").await?,
+    };
+    Ok(())
+}
+
+// ~settings rate_limits on | off, or suggest | steam <uses> <per seconds> <seconds apart>
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn rate_limits(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    if let Some(bucket) = args.current().and_then(Bucket::parse) {
+        args.advance();
+        let limit = match (args.single::<u32>(), args.single::<u32>(), args.single::<u32>()) {
+            (Ok(limit), Ok(time_span), Ok(delay)) => RateLimit::new(limit, time_span, delay),
+            _ => Err("Please provide the uses, per how many seconds and how many seconds apart, ex: rate_limits suggest 5 60 5".to_string())
+        };
+        let limit = match limit {
+            Ok(l) => l,
+            Err(why) => {
+                msg.reply(ctx, why).await?;
+                return Ok(());
+            }
+        };
+        let settings = update_settings(ctx, gid, |s| s.limits.set(bucket, limit)).await;
+        msg.reply(ctx, format!{"Rate limits are now: {}", settings.limits}).await?;
+        return Ok(());
+    }
+    let enabled = match parse_toggle(&mut args) {
+        Some(e) => e,
+        None => {
+            msg.reply(ctx, "Please provide 'on', 'off' or a limit, ex: rate_limits steam 4 120 10").await?;
+            return Ok(());
+        }
+    };
+    update_settings(ctx, gid, |s| s.rate_limits = enabled).await;
+    if enabled {
+        msg.reply(ctx, "Suggesting and steam lookups are now rate limited.").await?;
+    } else {
+        msg.reply(ctx, "Rate limits are now off.").await?;
+    }
+    Ok(())
+}
+
+// ~settings departed keep | archive | transfer @user
+#[command]
+#[required_permissions("MANAGE_GUILD")]
+async fn departed(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let policy = match args.single::<String>().map(|a| a.to_ascii_lowercase()) {
+        Ok(a) if a == "keep" => DepartedPolicy::Keep,
+        Ok(a) if a == "archive" => DepartedPolicy::Archive,
+        Ok(a) if a == "transfer" => match args.single::<UserId>() {
+            Ok(u) => DepartedPolicy::Transfer(u),
+            Err(_) => {
+                msg.reply(ctx, "Please mention who should receive the suggestions.").await?;
+                return Ok(());
+            }
+        },
+        _ => {
+            msg.reply(ctx, "Please provide 'keep', 'archive' or 'transfer @user'.").await?;
+            return Ok(());
+        }
+    };
+    let settings = update_settings(ctx, gid, |s| s.departed = policy).await;
+    msg.reply(ctx, format!{"Suggestions of members who leave are now {}.", settings.departed}).await?;
+    Ok(())
+}
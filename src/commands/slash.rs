@@ -0,0 +1,455 @@
+use serde_json::Value;
+use tracing::warn;
+
+use serenity::client::Context;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommands},
+    framework::standard::CommandResult,
+    model::{
+        channel::{Message, ReactionType},
+        id::UserId,
+        interactions::{
+            Interaction,
+            InteractionApplicationCommandCallbackDataFlags,
+            InteractionResponseType,
+            application_command::{
+                ApplicationCommand,
+                ApplicationCommandInteraction,
+                ApplicationCommandInteractionDataOption,
+                ApplicationCommandOptionType,
+            },
+            autocomplete::AutocompleteInteraction,
+        },
+        misc::Mentionable,
+    },
+};
+
+use game_randomizer::endpoints::steam;
+use game_randomizer::polls::{StrawPoll, MAX_ANSWERS};
+use game_randomizer::ratelimits::Bucket;
+use game_randomizer::suggestions::Filter;
+use game_randomizer::tags::normalize_tag;
+
+use crate::commands::listing::{self, Listing, ListingQuery, NEXT_EMOJI, PREV_EMOJI};
+use crate::commands::settings::{guild_settings, rate_limit};
+use crate::commands::{components, lifecycle, players, polls, suggestions, votes};
+use crate::commands::suggestions::GameSuggestions;
+
+/// Most choices discord shows while autocompleting.
+const MAX_CHOICES: usize = 25;
+/// Longest name discord accepts for a choice.
+const MAX_CHOICE_NAME: usize = 100;
+/// Players that can be compared at once with `/common_games`.
+const MAX_PLAYERS: usize = 4;
+
+type Options = [ApplicationCommandInteractionDataOption];
+
+/// Registers the slash commands globally, replacing any registered before.
+pub async fn register(ctx: &Context) {
+    let registered = ApplicationCommand::set_global_application_commands(ctx, commands).await;
+    if let Err(why) = registered {
+        warn!("Could not register slash commands: {:?}", why);
+    }
+}
+
+fn commands(c: &mut CreateApplicationCommands) -> &mut CreateApplicationCommands {
+    c.create_application_command(|c| c
+        .name("suggest")
+        .description("Suggest a game to play")
+        .create_option(|o| o
+            .name("store")
+            .description("Where the game is from")
+            .kind(ApplicationCommandOptionType::String)
+            .required(true)
+            .add_string_choice("Steam", "steam")
+            .add_string_choice("Plain text", "plain"))
+        .create_option(|o| o
+            .name("game")
+            .description("Title, or the id in the store")
+            .kind(ApplicationCommandOptionType::String)
+            .required(true)
+            .set_autocomplete(true))
+        .create_option(|o| o
+            .name("genre")
+            .description("Genre of a plain text suggestion")
+            .kind(ApplicationCommandOptionType::String))
+        .create_option(|o| o
+            .name("url")
+            .description("Link for a plain text suggestion")
+            .kind(ApplicationCommandOptionType::String)))
+    .create_application_command(|c| c
+        .name("list")
+        .description("List the suggested games")
+        .create_option(|o| o
+            .name("sort")
+            .description("Order of the list")
+            .kind(ApplicationCommandOptionType::String)
+            .add_string_choice("Date", "date")
+            .add_string_choice("Title", "title")
+            .add_string_choice("Suggester", "suggester")
+            .add_string_choice("Votes", "votes"))
+        .create_option(|o| o
+            .name("type")
+            .description("Only suggestions of this type")
+            .kind(ApplicationCommandOptionType::String)
+            .add_string_choice("Plain text", "plain")
+            .add_string_choice("Steam", "steam"))
+        .create_option(|o| o
+            .name("state")
+            .description("Only suggestions in this state")
+            .kind(ApplicationCommandOptionType::String)
+            .add_string_choice("Backlog", "backlog")
+            .add_string_choice("Played", "played")
+            .add_string_choice("Completed", "completed")
+            .add_string_choice("Dropped", "dropped"))
+        .create_option(|o| o
+            .name("genre")
+            .description("Only plain text suggestions of this genre")
+            .kind(ApplicationCommandOptionType::String))
+        .create_option(|o| o
+            .name("tags")
+            .description("Only suggestions with all of these tags, separated by spaces")
+            .kind(ApplicationCommandOptionType::String))
+        .create_option(|o| o
+            .name("all")
+            .description("Include games that were played or dropped")
+            .kind(ApplicationCommandOptionType::Boolean)))
+    .create_application_command(|c| c
+        .name("remove")
+        .description("Remove a suggestion")
+        .create_option(|o| o
+            .name("suggestion")
+            .description("The suggestion to remove")
+            .kind(ApplicationCommandOptionType::Integer)
+            .required(true)
+            .set_autocomplete(true)))
+    .create_application_command(|c| c
+        .name("random")
+        .description("Pick a random suggestion")
+        .create_option(|o| o
+            .name("tags")
+            .description("Only pick suggestions with all of these tags, separated by spaces")
+            .kind(ApplicationCommandOptionType::String)))
+    .create_application_command(|c| {
+        c.name("common_games").description("Find the games players have in common");
+        for i in 1..=MAX_PLAYERS {
+            c.create_option(|o| o
+                .name(format!{"player{}", i})
+                .description("A player with linked accounts")
+                .kind(ApplicationCommandOptionType::User)
+                .required(i <= 2));
+        }
+        c
+    })
+    .create_application_command(|c| c
+        .name("link")
+        .description("Link your steam account")
+        .create_option(|o| o
+            .name("steam")
+            .description("Steam id, profile link or vanity name")
+            .kind(ApplicationCommandOptionType::String)
+            .required(true)))
+    .create_application_command(poll_command)
+}
+
+fn poll_command(c: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    c.name("poll").description("Start a poll").create_option(|o| o
+        .name("question")
+        .description("What to ask")
+        .kind(ApplicationCommandOptionType::String)
+        .required(true));
+    for i in 1..=MAX_ANSWERS {
+        c.create_option(|o| o
+            .name(format!{"answer{}", i})
+            .description("An answer to vote for")
+            .kind(ApplicationCommandOptionType::String)
+            .required(i <= 2));
+    }
+    c
+}
+
+/// Answers slash commands and autocompletes their options.
+pub async fn handle(ctx: &Context, interaction: Interaction) {
+    let result = match interaction {
+        Interaction::ApplicationCommand(command) => run(ctx, &command).await,
+        Interaction::Autocomplete(partial) => autocomplete(ctx, &partial).await,
+        _ => Ok(())
+    };
+    if let Err(why) = result {
+        warn!("Failed answering interaction: {:?}", why);
+    }
+}
+
+fn option<'a>(options: &'a Options, name: &str) -> Option<&'a Value> {
+    options.iter().find(|o| o.name == name).and_then(|o| o.value.as_ref())
+}
+
+fn string(options: &Options, name: &str) -> Option<String> {
+    option(options, name).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+fn integer(options: &Options, name: &str) -> Option<i64> {
+    option(options, name).and_then(|v| v.as_i64())
+}
+
+fn boolean(options: &Options, name: &str) -> Option<bool> {
+    option(options, name).and_then(|v| v.as_bool())
+}
+
+fn user(options: &Options, name: &str) -> Option<UserId> {
+    option(options, name).and_then(|v| v.as_str()).and_then(|s| s.parse::<u64>().ok()).map(UserId)
+}
+
+/// Tags typed into an option, separated by spaces.
+fn tags(options: &Options, name: &str) -> Vec<String> {
+    string(options, name).unwrap_or_default()
+        .split_whitespace()
+        .filter_map(normalize_tag)
+        .collect()
+}
+
+/// Answers only the user who ran the command.
+async fn ephemeral(ctx: &Context, command: &ApplicationCommandInteraction, content: impl ToString) -> CommandResult {
+    command.create_interaction_response(ctx, |r| r
+        .kind(InteractionResponseType::ChannelMessageWithSource)
+        .interaction_response_data(|d| d
+            .content(content)
+            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL))
+    ).await?;
+    Ok(())
+}
+
+/// Fills in a deferred answer, returning the posted message.
+async fn respond(ctx: &Context, command: &ApplicationCommandInteraction, content: impl ToString) -> serenity::Result<Message> {
+    command.edit_original_interaction_response(ctx, |r| r.content(content)).await
+}
+
+/// The options of `/list` as a listing query.
+fn listing_query(options: &Options) -> Result<ListingQuery, String> {
+    let mut query = ListingQuery::default();
+    if let Some(sort) = string(options, "sort") {
+        query.sort = sort.parse()?;
+    }
+    if let Some(kind) = string(options, "type") {
+        query.filter.kind = Some(kind.parse()?);
+    }
+    if let Some(state) = string(options, "state") {
+        query.filter.state = Some(state.parse()?);
+    }
+    query.filter.genre = string(options, "genre").map(|g| g.to_ascii_lowercase());
+    query.filter.tags = tags(options, "tags");
+    query.filter.all = boolean(options, "all").unwrap_or(false);
+    Ok(query)
+}
+
+async fn run(ctx: &Context, command: &ApplicationCommandInteraction) -> CommandResult {
+    let gid = match command.guild_id {
+        Some(g) => g,
+        None => return ephemeral(ctx, command, "Commands only work in servers.").await
+    };
+    if let Some(channel) = guild_settings(ctx, Some(gid)).await.bot_channel {
+        if channel != command.channel_id {
+            return ephemeral(ctx, command, format!{"Use commands in {}.", channel.mention()}).await;
+        }
+    }
+    // limited like their prefix commands
+    let bucket = match command.data.name.as_str() {
+        "suggest" => Some(Bucket::Suggest),
+        "link" | "common_games" => Some(Bucket::Steam),
+        _ => None
+    };
+    if let Some(bucket) = bucket {
+        if let Err(why) = rate_limit(ctx, Some(gid), command.user.id, bucket).await {
+            return ephemeral(ctx, command, why).await;
+        }
+    }
+    // store lookups can take longer than discord waits for an answer
+    command.defer(ctx).await?;
+    let options = &command.data.options;
+    let author = command.user.id;
+    match command.data.name.as_str() {
+        "suggest" => {
+            let store = string(options, "store").unwrap_or_default();
+            let game = string(options, "game").unwrap_or_default();
+            let suggested = suggestions::suggest(
+                ctx, gid, author, &store, &game, string(options, "genre"), string(options, "url")
+            ).await;
+            match suggested {
+                Ok((id, response)) => {
+                    let posted = respond(ctx, command, response).await?;
+                    votes::track(ctx, &posted, gid, id).await?;
+                },
+                Err(why) => { respond(ctx, command, why).await?; }
+            };
+        },
+        "list" => {
+            let query = match listing_query(options) {
+                Ok(q) => q,
+                Err(why) => {
+                    respond(ctx, command, why).await?;
+                    return Ok(());
+                }
+            };
+            let listing = Listing { guild: gid, query, page: 0 };
+            let page = listing::render(ctx, &listing).await;
+            let posted = command.edit_original_interaction_response(ctx, |r| r
+                .create_embed(|e| page.embed(e))
+                .components(|c| components::vote_menus(c, &page.entries))
+            ).await?;
+            if page.pages > 1 {
+                posted.react(ctx, ReactionType::Unicode(PREV_EMOJI.to_string())).await?;
+                posted.react(ctx, ReactionType::Unicode(NEXT_EMOJI.to_string())).await?;
+                listing::track(ctx, posted.id, listing).await;
+            }
+        },
+        "remove" => {
+            let id = integer(options, "suggestion").and_then(|i| u32::try_from(i).ok());
+            respond(ctx, command, suggestions::remove(ctx, gid, author, id).await).await?;
+        },
+        "random" => {
+            let tags = tags(options, "tags");
+            match suggestions::pick(ctx, gid, &tags, &[]).await {
+                Ok((id, response)) => {
+                    let posted = command.edit_original_interaction_response(ctx, |r| r
+                        .content(response)
+                        .components(|c| components::pick_buttons(c, id, &tags))
+                    ).await?;
+                    votes::track(ctx, &posted, gid, id).await?;
+                    lifecycle::track_pick(ctx, &posted, gid, id).await?;
+                },
+                Err(why) => { respond(ctx, command, why).await?; }
+            };
+        },
+        "common_games" => {
+            let mut users: Vec<UserId> = Vec::new();
+            for i in 1..=MAX_PLAYERS {
+                if let Some(u) = user(options, &format!{"player{}", i}) {
+                    if !users.contains(&u) {
+                        users.push(u);
+                    }
+                }
+            }
+            if users.len() < 2 {
+                respond(ctx, command, "Pick at least two different players to find common games.").await?;
+                return Ok(());
+            }
+            match players::find_common(ctx, gid, &users).await {
+                Ok(found) => {
+                    let menu = found.titles.len() >= 2;
+                    let last = found.responses.len().saturating_sub(1);
+                    for (i, response) in found.responses.into_iter().enumerate() {
+                        // the last message lets players start a poll from the results
+                        let titles = if menu && i == last { &found.titles[..] } else { &[] };
+                        if i == 0 {
+                            command.edit_original_interaction_response(ctx, |r| {
+                                r.content(response);
+                                if !titles.is_empty() {
+                                    r.components(|c| components::poll_menu(c, titles));
+                                }
+                                r
+                            }).await?;
+                        } else {
+                            command.create_followup_message(ctx, |f| {
+                                f.content(response);
+                                if !titles.is_empty() {
+                                    f.components(|c| components::poll_menu(c, titles));
+                                }
+                                f
+                            }).await?;
+                        }
+                    }
+                },
+                Err(why) => { respond(ctx, command, why).await?; }
+            };
+        },
+        "link" => {
+            let input = string(options, "steam").unwrap_or_default();
+            match players::link_steam(ctx, author, &input).await {
+                Ok(response) | Err(response) => respond(ctx, command, response).await?,
+            };
+        },
+        "poll" => {
+            let mut poll = StrawPoll::new(author, string(options, "question").unwrap_or_default());
+            for i in 1..=MAX_ANSWERS {
+                if let Some(answer) = string(options, &format!{"answer{}", i}) {
+                    if let Err(why) = poll.add_answer(answer) {
+                        respond(ctx, command, why).await?;
+                        return Ok(());
+                    }
+                }
+            }
+            if !poll.is_ready() {
+                respond(ctx, command, format!{"Polls need between 2 and {} answers.", MAX_ANSWERS}).await?;
+                return Ok(());
+            }
+            let posted = respond(ctx, command, polls::render(&poll)).await?;
+            polls::open(ctx, &posted, poll).await?;
+        },
+        _ => { respond(ctx, command, "Unknown command.").await?; }
+    };
+    Ok(())
+}
+
+fn choice_name(name: &str) -> String {
+    name.chars().take(MAX_CHOICE_NAME).collect()
+}
+
+/// Offers steam titles for `/suggest` and the guild's suggestions for
+/// `/remove` as the user types.
+async fn autocomplete(ctx: &Context, partial: &AutocompleteInteraction) -> CommandResult {
+    let options = &partial.data.options;
+    let focused = match options.iter().find(|o| o.focused) {
+        Some(f) => f,
+        None => return Ok(())
+    };
+    // partial values arrive as typed, even for integer options
+    let typed = match &focused.value {
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        None => String::new()
+    };
+    match (partial.data.name.as_str(), focused.name.as_str()) {
+        ("suggest", "game") => {
+            let is_steam = string(options, "store").is_none_or(|s| s == "steam");
+            let apps = if is_steam && !typed.trim().is_empty() {
+                let rlock = ctx.data.read().await;
+                let steam_inner = rlock.get::<steam::Client>().expect("no global steam::Client");
+                let steam_lock = steam_inner.lock().await;
+                steam_lock.search_apps(&typed, MAX_CHOICES)
+            } else {
+                Vec::new()
+            };
+            partial.create_autocomplete_response(ctx, |r| {
+                for app in apps {
+                    r.add_string_choice(choice_name(&app.name), app.id);
+                }
+                r
+            }).await?;
+        },
+        ("remove", "suggestion") => {
+            let typed = typed.trim().trim_start_matches('#').to_lowercase();
+            let found: Vec<(u32, String)> = match partial.guild_id {
+                Some(gid) => {
+                    let rlock = ctx.data.read().await;
+                    let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
+                    let filter = Filter { all: true, ..Filter::default() };
+                    inner.get(&gid).map(|book| book.list(&filter).into_iter()
+                        .filter(|s| s.id.to_string().starts_with(&typed) || s.title().to_lowercase().contains(&typed))
+                        .take(MAX_CHOICES)
+                        .map(|s| (s.id, choice_name(&format!{"#{} {}", s.id, s.title()})))
+                        .collect())
+                    .unwrap_or_default()
+                },
+                None => Vec::new()
+            };
+            partial.create_autocomplete_response(ctx, |r| {
+                for (id, name) in found {
+                    r.add_int_choice(name, id as i64);
+                }
+                r
+            }).await?;
+        },
+        _ => {}
+    };
+    Ok(())
+}
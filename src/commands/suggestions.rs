@@ -1,484 +1,424 @@
-use std::fmt;
 use std::collections::HashMap;
+use chrono::Utc;
 use serde::{Serialize, Deserialize};
+use tracing::info;
 
 use serenity::prelude::*;
 use serenity::client::Context;
-use serenity::model::id::GuildId;
+use serenity::model::id::{GuildId, UserId};
 
 use serenity::{
-    async_trait,
     framework::standard::{
         macros::{command, group},
         Args,
         CommandResult
     },
-    model::{
-        channel::Message,
-        id::UserId,
-    },
+    model::channel::Message,
     utils::{
         MessageBuilder
     }
 };
-use crate::endpoints::steam;
-
-#[derive(Deserialize,Serialize)]
-pub enum Suggestion {
-    Steam(UserId, steam::App),
-    PlainText(UserId, TextSuggestion)
-}
-impl<'a> Suggestion {
-    pub fn title(&'a self) -> &'a str {
-        use self::Suggestion::*;
-        match self {
-            Steam(_, app) => app.name.as_ref(),
-            PlainText(_,app) => app.title.as_ref()
-        }
-    }
-    pub fn user(&self) -> UserId {
-        use self::Suggestion::*;
-        match self {
-            Steam(user, _) => *user,
-            PlainText(user, _) => *user
-        }
-    }
-}
-impl PartialEq for Suggestion {
-    fn eq(&self, other: &Suggestion) -> bool {
-        use self::Suggestion::*;
-        match self {
-            Steam(_, s_app) => match other {
-                Steam(_, o_app) => s_app.id == o_app.id,
-                PlainText(_, o_app) => s_app.name == o_app.title,
-            },
-            PlainText(_, s_app) => match other {
-                Steam(_, o_app) => s_app.title == o_app.name,
-                PlainText(_, o_app) => s_app.title == o_app.title,
-            },
-        }
-    }
-}
-impl Default for Suggestion {
-    fn default() -> Self {
-        Suggestion::PlainText(UserId::default(), TextSuggestion::default())
-    }
-}
+use game_randomizer::endpoints::{steam, GameSources, Source};
+use game_randomizer::picker;
+use game_randomizer::suggestions::{parse_id, SuggestedGame, SuggestionBook, TextSuggestion};
+use game_randomizer::tags::{normalize_tag, steam_tags};
 
-#[derive(Debug,Hash,Deserialize,Serialize)]
-pub struct TextSuggestion {
-    pub title: String,
-    pub genre: Option<String>,
-    pub url: Option<String>,
-}
-impl Default for TextSuggestion {
-    fn default() -> Self {
-        TextSuggestion {
-            title: String::new(),
-            genre: None, 
-            url: None
-        }
-    }
-}
-impl fmt::Display for TextSuggestion {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut rst = write!(f, "Title: {}", self.title);
-        if let Some(genre) = &self.genre {
-            rst = write!(f, " | Genre: {}", genre);
-        }
-        if let Some(url) =&self.url {
-            rst = write!(f, " | Url: {}", url);
-        }
-        rst
-    }
-}
+use crate::commands::settings::{guild_settings, SUGGESTING_CHECK};
+use crate::commands::permissions::is_moderator;
+use crate::commands::listing::{self, Listing, ListingQuery};
+use crate::commands::{components, lifecycle, votes};
+use crate::commands::lifecycle::PickVetoes;
 
 #[derive(Deserialize,Serialize)]
 pub struct GameSuggestions;
 impl TypeMapKey for GameSuggestions {
-    type Value = HashMap<GuildId, Vec<Suggestion>>;
-}
-impl GameSuggestions {
-    pub fn new() -> HashMap<GuildId, Vec<Suggestion>> {
-        HashMap::new()
-    }
+    type Value = HashMap<GuildId, SuggestionBook>;
 }
 
 #[group]
-#[commands(add_suggestion, list_suggestions, remove_suggestion)]
+#[commands(add_suggestion, list_suggestions, remove_suggestion, edit_suggestion, random_suggestion)]
 pub struct Suggestions;
 // ~add_game title genre url
 #[command]
 #[aliases("suggest")]
+#[checks(Suggesting)]
 async fn add_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    
-    match args.single_quoted::<String>() {
-        Ok(t) => {
-            match t.to_ascii_lowercase().trim() {
-                "plain" => add_text_suggestion(ctx, msg, args).await,
-                "steam" => add_steam_suggestion(ctx, msg, args).await,
-                _ => {
-                    msg.reply(ctx, "Invalid suggestion type. Try 'plain' or 'steam'.").await?;
-                    Ok(())
-                }
-            }
-        },
-        Err(_) => { 
-            println!{"Failed parsing add_suggestion"};
-            Ok(())
+    let gid = match msg.guild_id {
+        Some(g) => g,
+        None => {
+            msg.reply(ctx, "Cannot add suggestion to non-guild channel.").await?;
+            return Ok(()); // todo error
+        }
+    };
+    let kind = match args.single_quoted::<String>() {
+        Ok(t) => t,
+        Err(_) => {
+            info!{"Failed parsing add_suggestion"};
+            return Ok(());
         },
-    }
+    };
+    // suggest plain "title" genre url
+    // suggest steam 01234
+    // suggest steam "some title"
+    let game = args.single_quoted::<String>().unwrap_or_default();
+    let genre = args.single_quoted::<String>().ok();
+    let url = args.single_quoted::<String>().ok();
+    let (id, response) = match suggest(ctx, gid, msg.author.id, &kind, &game, genre, url).await {
+        Ok(s) => s,
+        Err(why) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
+    };
+    let posted = msg.reply(ctx, response).await?;
+    votes::track(ctx, &posted, gid, id).await?;
+    Ok(())
 }
 
+// ~list_suggestions --sort title --type plain --genre rpg
 #[command]
 #[aliases("suggestions")]
 async fn list_suggestions(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let gid = match msg.guild_id {
         Some(g) => g,
         None => {
-            msg.reply(ctx, "Cannot add suggestion to non-guild channel.").await?;
+            msg.reply(ctx, "Cannot list suggestions in non-guild channel.").await?;
             return Ok(()); // todo error
         }
     };
-    let mut response = MessageBuilder::new();
-    response.push_line("Currently suggested games");
-    let games = {
-        use self::Suggestion::*;
-        let rlock = ctx.data.read().await;
-        let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
-        let mut game_block = String::new();
-        if let Some(existing) = inner.get(&gid) {
-            let _ = existing.iter().map(|s|{
-                match s {
-                    PlainText(_, app) => {
-                        game_block.push_str("Title: ");
-                        game_block.push_str(&app.title);
-                        if let Some(genre) = &app.genre {
-                            game_block.push_str(" Genre: ");
-                            game_block.push_str(genre);
-                        }
-                        if let Some(url) = &app.url {
-                            game_block.push_str("Url: ");
-                            game_block.push_str(url);
-                        }
-                    },
-                    Steam(_, app) => {
-                        game_block.push_str("Steam: ");
-                        game_block.push_str(&app.name);
-                        game_block.push_str(" - ");
-                        game_block.push_str(&app.url());
-                    }
-                };
-                game_block.push_str("\r\n");
-            }).collect::<()>();
-        };
-        game_block
+    let query = match ListingQuery::parse(&mut args) {
+        Ok(q) => q,
+        Err(why) => {
+            msg.reply(ctx, why).await?;
+            return Ok(());
+        }
     };
-    response.push_codeblock(games, None);
-    msg.reply(ctx, response.build()).await?;
-    Ok(())
+    listing::post(ctx, msg, Listing { guild: gid, query, page: 0 }).await
 }
 
+// ~random [tags...], ex: random co-op shooter
 #[command]
-async fn remove_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-
-    match args.single_quoted::<String>() {
-        Ok(t) => {
-            match t.to_ascii_lowercase().trim() {
-                "plain" => remove_text_suggestion(ctx, msg, args).await,
-                "steam" => remove_steam_suggestion(ctx, msg, args).await,
-                _ => {
-                    msg.reply(ctx, "Invalid suggestion type. Try 'plain' or 'steam'.").await?;
-                    Ok(())
-                }
-            }
-        },
-        Err(_) => { 
-            println!{"Failed parsing remove_suggestion"};
-            Ok(())
-        },
-    }
-}
-
-async fn remove_text_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+#[aliases("random")]
+async fn random_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let gid = match msg.guild_id {
         Some(g) => g,
         None => {
-            msg.reply(ctx, "Cannot add suggestion to non-guild channel.").await?;
+            msg.reply(ctx, "Cannot pick suggestions in non-guild channel.").await?;
             return Ok(()); // todo error
         }
     };
-    
-    let mut suggestion = TextSuggestion::default();
-    let mut response = MessageBuilder::new();
-
-    match args.single_quoted::<String>() {
-        Ok(t) => {
-            suggestion.title = t.trim().to_string();
-        },
-        Err(_) => {
-            msg.reply(ctx, "Invalid command, no title provided").await?;
+    let tags: Vec<String> = args.quoted().iter::<String>()
+        .flatten()
+        .filter_map(|t| normalize_tag(&t))
+        .collect();
+    let (id, response) = match pick(ctx, gid, &tags, &[]).await {
+        Ok(p) => p,
+        Err(why) => {
+            msg.reply(ctx, why).await?;
             return Ok(());
-        },
-    };
-    match args.single_quoted::<String>() {
-        Ok(g) => {
-            suggestion.genre = Some(g.clone());
-        },
-        Err(_) => {},
-    };
-    match args.single_quoted::<String>() {
-        Ok(u) => {
-            suggestion.url = Some(u.clone());
-        },
-        Err(_) => {},
-    };
-    let mut idx = std::usize::MAX;
-    let suggestion = Suggestion::PlainText(msg.author.id, suggestion);
-    let tmp = suggestion.title().to_ascii_lowercase();
-    { // read lock
-        use self::Suggestion::*;
-        let rlock = ctx.data.read().await;
-        let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
-        if let Some(existing) = inner.get(&gid) {
-            idx = match (*existing).iter()
-                .map(|sug| sug.title() )
-                .position(|s| s.to_ascii_lowercase() == tmp) {
-                    Some(i) => i,
-                    None => std::usize::MAX,
-                };
-        };
-    }
-    { // write lock
-        if idx == std::usize::MAX {
-            response.push("No suggestion with that title.");
-        } else {
-            let mut wlock = ctx.data.write().await;
-            let mut winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
-            if let Some(existing) = winner.get_mut(&gid) {
-                if existing[idx].user() == suggestion.user() {
-                    let _ = existing.swap_remove(idx);
-                    response.push("Removed suggestion: ")
-                        .push_line(suggestion.title());
-                } else {
-                    response.push_line("Found suggestion but cannot be removed by you.");
-                }
-            };
         }
-    }
-    msg.reply(ctx, response.build()).await?;
+    };
+    let posted = msg.channel_id.send_message(ctx, |m| m
+        .reference_message(msg)
+        .content(response)
+        .components(|c| components::pick_buttons(c, id, &tags))
+    ).await?;
+    votes::track(ctx, &posted, gid, id).await?;
+    lifecycle::track_pick(ctx, &posted, gid, id).await?;
     Ok(())
 }
-async fn remove_steam_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+
+// ~remove_suggestion #12
+// ~remove_suggestion plain "title"
+// ~remove_suggestion steam 730
+#[command]
+async fn remove_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let gid = match msg.guild_id {
         Some(g) => g,
         None => {
-            msg.reply(ctx, "Cannot add suggestion to non-guild channel.").await?;
+            msg.reply(ctx, "Cannot remove suggestion from non-guild channel.").await?;
             return Ok(()); // todo error
         }
     };
-    
-    let mut response = MessageBuilder::new();
-    let suggestion = match args.single_quoted::<String>() {
+    let kind = match args.single_quoted::<String>() {
+        Ok(t) => t.trim().to_ascii_lowercase(),
+        Err(_) => {
+            msg.reply(ctx, "Please provide a suggestion id like #12, or 'plain' or a store and a title.").await?;
+            return Ok(());
+        },
+    };
+    if let Some(id) = parse_id(&kind) {
+        msg.reply(ctx, remove(ctx, gid, msg.author.id, Some(id)).await).await?;
+        return Ok(());
+    }
+    let target = match args.single_quoted::<String>() {
         Ok(t) => t.trim().to_string(),
         Err(_) => {
-            msg.reply(ctx, "No id or name provided").await?;
+            msg.reply(ctx, "Invalid command, no title or id provided").await?;
             return Ok(());
         },
     };
-    let suggestion = { // read-lock to find game from steam apps
-        let rlock = ctx.data.read().await;
-        let steam_inner = rlock.get::<steam::Client>().expect("no global steam::Client");
-        let app = match suggestion.parse::<u32>() {
-            Ok(id) => { // parsed as int, use as id
-                match (**steam_inner).lock().await.game_by_id(id).await {
-                    Ok(a) => a,
-                    Err(_) => {
-                        msg.reply(ctx, "Invalid Steam Id requested.").await?;
-                        return Ok(());
-                    }
-                }
-            },
-            Err(_) => { // wasnt an integer, treat as title
-                match (**steam_inner).lock().await.game_by_name(&suggestion).await {
-                    Ok(a) => a,
-                    Err(_) => {
-                        msg.reply(ctx, "Invalid Steam Name requested.").await?;
-                        return Ok(());
-                    }
-                }
-            }
-        };
-        Suggestion::Steam(
-            msg.author.id,
-            app
-        )
+    let source = match (kind.as_str(), kind.parse::<Source>()) {
+        ("plain", _) => None,
+        (_, Ok(source)) => Some(source),
+        _ => {
+            msg.reply(ctx, "Invalid suggestion type. Try 'plain' or 'steam'.").await?;
+            return Ok(());
+        }
     };
-
-    let mut idx = std::usize::MAX;
-    let tmp = suggestion.title().to_ascii_lowercase();
-    { // read lock
+    let id = { // read lock
         let rlock = ctx.data.read().await;
         let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
-        if let Some(existing) = inner.get(&gid) {
-            idx = match (*existing).iter()
-                .map(|sug| sug.title() )
-                .position(|s| s.to_ascii_lowercase() == tmp) {
-                    Some(i) => i,
-                    None => std::usize::MAX,
-                };
-        };
-    }
+        let existing = inner.get(&gid);
+        match source {
+            None => existing.and_then(|e| e.find_by_title(&target)).map(|s| s.id),
+            Some(source) => existing.and_then(|e| e.find_in_store(source, &target)).map(|s| s.id),
+        }
+    };
+    match id {
+        Some(_) => msg.reply(ctx, remove(ctx, gid, msg.author.id, id).await).await?,
+        None => msg.reply(ctx, "No suggestion with that title.").await?,
+    };
+    Ok(())
+}
+
+/// Removes a suggestion on behalf of a user, returning what to tell them.
+pub async fn remove(ctx: &Context, gid: GuildId, user: UserId, id: Option<u32>) -> String {
+    let moderator = is_moderator(ctx, gid, user).await;
+    let mut response = MessageBuilder::new();
     { // write lock
-        if idx == std::usize::MAX {
-            response.push("No suggestion with that title.");
-        } else {
-            let mut wlock = ctx.data.write().await;
-            let mut winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
-            if let Some(existing) = winner.get_mut(&gid) {
-                if existing[idx].user() == suggestion.user() {
-                    let _ = existing.swap_remove(idx);
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+        match (winner.get_mut(&gid), id) {
+            (Some(existing), Some(id)) => match existing.remove(id, user, moderator) {
+                Ok(removed) => {
                     response.push("Removed suggestion: ")
-                        .push_line(suggestion.title());
-                } else {
-                    response.push_line("Found suggestion but cannot be removed by you.");
+                        .push_line(removed.title());
+                },
+                Err(why) => {
+                    response.push_line(why);
                 }
-            };
-        }
+            },
+            _ => {
+                response.push("No suggestion with that id.");
+            }
+        };
     }
-    msg.reply(ctx, response.build()).await?;
-    Ok(())
+    response.build()
 }
 
-
-async fn add_text_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let mut suggestion = TextSuggestion::default();
-    let mut response = String::new(); //TODO switch to messagebuilder
-    
-    match args.single_quoted::<String>() {
-        Ok(t) => {
-            suggestion.title = t.trim().to_string();
-            response.push_str("Added: ");
-            response.push_str(&suggestion.title);
-        },
-        Err(_) => {},
-    };
-    match args.single_quoted::<String>() {
-        Ok(g) => {
-            response.push_str(" genre: "); 
-            response.push_str(&g);
-            suggestion.genre = Some(g.clone());
-        },
-        Err(_) => {},
-    };
-    match args.single_quoted::<String>() {
-        Ok(u) => {
-            response.push_str(" url: ");
-            response.push_str(&u);
-            suggestion.url = Some(u.clone());
-        },
-        Err(_) => {},
-    };
+// ~edit_suggestion "title" genre|url|title "new value"
+// ~edit_suggestion #12 genre|url|title "new value"
+#[command]
+#[aliases("edit")]
+async fn edit_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let gid = match msg.guild_id {
         Some(g) => g,
         None => {
-            msg.reply(ctx, "Cannot add suggestion to non-guild channel.").await?;
+            msg.reply(ctx, "Cannot edit suggestion in non-guild channel.").await?;
             return Ok(()); // todo error
         }
     };
-    {
-        // await any other writers first!
-        let rlock = ctx.data.read().await;
-        let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
-        if let Some(existing) = inner.get(&gid) { // have existing suggestions
-            let tmp = suggestion.title.to_ascii_lowercase();
-            if (*existing).iter().any(|s| s.title().to_ascii_lowercase() == tmp) {
-                msg.reply(ctx, "This game has been suggested already, thanks!").await?;
-                return Ok(());
-            }
+    let (target, field, value) = match (
+        args.single_quoted::<String>(),
+        args.single_quoted::<String>(),
+        args.single_quoted::<String>(),
+    ) {
+        (Ok(t), Ok(f), Ok(v)) => (t.trim().to_string(), f.to_ascii_lowercase(), v.trim().to_string()),
+        _ => {
+            msg.reply(ctx, "Usage: edit_suggestion \"title\"|#id genre|url|title \"value\"").await?;
+            return Ok(());
         }
+    };
+    if !matches!(field.as_str(), "genre" | "url" | "title") {
+        msg.reply(ctx, "Invalid field. Try 'genre', 'url' or 'title'.").await?;
+        return Ok(());
+    }
+    if target.is_empty() || value.is_empty() {
+        msg.reply(ctx, format!{"Please provide the suggestion and a new {}.", field}).await?;
+        return Ok(());
     }
-    // add to suggestions 
-    {
+    msg.reply(ctx, edit(ctx, gid, msg.author.id, &target, &field, value).await).await?;
+    Ok(())
+}
+
+/// Edits a field of a plain text suggestion on behalf of a user, returning
+/// what to tell them.
+async fn edit(ctx: &Context, gid: GuildId, user: UserId, target: &str, field: &str, value: String) -> String {
+    let moderator = is_moderator(ctx, gid, user).await;
+    let mut response = MessageBuilder::new();
+    { // write lock
         let mut wlock = ctx.data.write().await;
-        let mut inner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
-        let suggestion = Suggestion::PlainText(msg.author.id, suggestion);
-        if let Some(existing) = inner.get_mut(&gid) { // add to existing suggestions
-            existing.push(suggestion);
-        } else {
-            let suggestions = vec![ suggestion ];
-            inner.insert(gid, suggestions);
+        let winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+        let missing = match parse_id(target) {
+            Some(_) => "No suggestion with that id.",
+            None => "No suggestion with that title.",
+        };
+        let existing = match winner.get_mut(&gid) {
+            Some(e) => e,
+            None => return missing.to_string()
+        };
+        let found = match parse_id(target) {
+            Some(id) => existing.get(id),
+            None => existing.find_by_title(target),
+        };
+        let id = match found {
+            Some(s) => s.id,
+            None => return missing.to_string()
+        };
+        if field == "title" {
+            if let Some(other) = existing.find_by_title(&value) {
+                if other.id != id {
+                    return "This game has been suggested already, thanks!".to_string();
+                }
+            }
         }
+        let suggestion = existing.get_mut(id).expect("suggestion found above");
+        let owner = suggestion.user();
+        match &mut suggestion.game {
+            SuggestedGame::PlainText(_) if owner != user && !moderator => {
+                response.push_line("Found suggestion but cannot be edited by you.");
+            },
+            SuggestedGame::PlainText(app) => {
+                match field {
+                    "genre" => {
+                        // the old genre's tag goes with it
+                        if let Some(old) = app.genre.as_deref().and_then(normalize_tag) {
+                            suggestion.tags.remove(&old);
+                        }
+                        suggestion.tags.extend(normalize_tag(&value));
+                        app.genre = Some(value);
+                    },
+                    "url" => app.url = Some(value),
+                    _ => app.title = value,
+                };
+                response.push("Updated suggestion: ").push_line(app.to_string());
+            },
+            SuggestedGame::Store(app) => {
+                response.push_line(format!{"{} suggestions are kept in sync with the store and cannot be edited.", app.source});
+            }
+        };
     }
-    msg.reply(ctx, response).await?;
-    Ok(())
+    response.build()
 }
-async fn add_steam_suggestion(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    // suggest steam 01234
-    // suggest steam "some title"
-    // "suggest steam" is already removed
-    let gid = match msg.guild_id {
-        Some(g) => g,
-        None => {
-            msg.reply(ctx, "Cannot add suggestion to non-guild channel.").await?;
-            return Ok(()); // todo error
+
+/// Picks a random suggestion for the guild other than those in `skip` or
+/// vetoed for the night, returning its id and the message announcing it.
+pub async fn pick(ctx: &Context, gid: GuildId, tags: &[String], skip: &[u32]) -> Result<(u32, String), String> {
+    let weights = guild_settings(ctx, Some(gid)).await.weights;
+    let vetoes = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<PickVetoes>().expect("no pick vetoes stored").clone()
+    };
+    let mut skip = skip.to_vec();
+    if let Some(vetoed) = vetoes.lock().await.get_mut(&gid) {
+        skip.extend(vetoed.active(Utc::now()));
+    }
+    let rlock = ctx.data.read().await;
+    let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
+    let empty = SuggestionBook::default();
+    let book = inner.get(&gid).unwrap_or(&empty);
+    let pick = picker::pick(book, &weights, tags, &skip, &mut rand::thread_rng())?;
+    let mut response = MessageBuilder::new();
+    response.push("How about: ").push_bold_safe(pick.title());
+    match &pick.game {
+        SuggestedGame::Store(app) => { response.push(" - ").push(app.url()); },
+        SuggestedGame::PlainText(app) => {
+            if let Some(url) = &app.url {
+                response.push(" - ").push(url);
+            }
         }
     };
-    let suggestion = match args.single_quoted::<String>() {
-        Ok(t) => t.trim().to_string(),
-        Err(_) => {
-            msg.reply(ctx, "No id or name provided").await?;
-            return Ok(());
+    response.push_line("")
+        .push("React with ").push(lifecycle::PLAYED_EMOJI).push(" once it has been played.");
+    Ok((pick.id, response.build()))
+}
+
+/// Adds a suggestion from a user, returning its id and the message
+/// confirming it. `kind` is 'plain' or a store, `game` is the title of
+/// plain suggestions or an id or name in the store.
+pub async fn suggest(
+    ctx: &Context,
+    gid: GuildId,
+    user: UserId,
+    kind: &str,
+    game: &str,
+    genre: Option<String>,
+    url: Option<String>,
+) -> Result<(u32, String), String> {
+    let settings = guild_settings(ctx, Some(gid)).await;
+    if let Some(max) = settings.max_suggestions {
+        let count = {
+            let rlock = ctx.data.read().await;
+            let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
+            inner.get(&gid).map_or(0, |b| b.active_count(user))
+        };
+        if count >= max {
+            return Err(format!{"You already have {} active suggestions, remove one or wait for it to be played before adding more.", count});
+        }
+    }
+    let game = game.trim();
+    let (app, tags) = match kind.to_ascii_lowercase().trim() {
+        "plain" if !settings.allow_plain => {
+            return Err("Plain text suggestions are disabled on this server, try 'steam'.".to_string());
+        },
+        "plain" => {
+            let suggestion = TextSuggestion { title: game.to_string(), genre, url };
+            (SuggestedGame::PlainText(suggestion), Vec::new())
         },
+        kind => match kind.parse::<Source>() {
+            Ok(_) if game.is_empty() => return Err("No id or name provided".to_string()),
+            Ok(source) => store_suggestion(ctx, gid, source, game).await?,
+            Err(_) => return Err("Invalid suggestion type. Try 'plain' or 'steam'.".to_string())
+        }
     };
-    let app = { // read-lock to find game from steam apps and see about matches
+    // add to suggestions
+    let mut wlock = ctx.data.write().await;
+    let inner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+    let added = inner.entry(gid).or_default().add(user, app)?;
+    added.tags.extend(tags);
+    Ok((added.id, format!{"Added: {}", added}))
+}
+
+/// Finds a game in a store by id or name, with its steam genres and
+/// categories as tags.
+async fn store_suggestion(ctx: &Context, gid: GuildId, source: Source, game: &str) -> Result<(SuggestedGame, Vec<String>), String> {
+    let app = { // read-lock to find game from the store and see about matches
         let rlock = ctx.data.read().await;
-        let steam_inner = rlock.get::<steam::Client>().expect("no global steam::Client");
-        let app = match suggestion.parse::<u32>() {
-            Ok(id) => { // parsed as int, use as id
-                match (**steam_inner).lock().await.game_by_id(id).await {
-                    Ok(a) => a,
-                    Err(_) => {
-                        msg.reply(ctx, "Invalid Steam Id requested.").await?;
-                        return Ok(());
-                    }
-                }
-            },
-            Err(_) => { // wasnt an integer, treat as title
-                match (**steam_inner).lock().await.game_by_name(&suggestion).await {
-                    Ok(a) => a,
-                    Err(_) => {
-                        msg.reply(ctx, "Invalid Steam Name requested.").await?;
-                        return Ok(());
-                    }
-                }
-            }
+        let store = match rlock.get::<GameSources>().expect("no game sources").get(&source) {
+            Some(s) => s.clone(),
+            None => return Err(format!{"{} is not available right now.", source})
         };
-        let app = Suggestion::Steam(
-            msg.author.id,
-            app
-        );
+        let store = store.lock().await;
+        let app = match store.lookup(game).await {
+            Ok(a) => a,
+            Err(_) => return Err(format!{"No {} game with that id or name.", source})
+        };
+        let app = SuggestedGame::Store(app);
         // await any other writers first!
         let inner = rlock.get::<GameSuggestions>().expect("no suggestions read data");
         if let Some(existing) = inner.get(&gid) {
-            if (*existing).iter().any(|sug| *sug == app) {
-                msg.reply(ctx, "This game has been suggested already, thanks!").await?;
-                return Ok(());
+            if existing.duplicate_of(&app).is_some() {
+                return Err("This game has been suggested already, thanks!".to_string());
             }
         }
         app
     };
-    // add to suggestions 
-    {
-        let mut wlock = ctx.data.write().await;
-        let mut inner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
-        if let Some(existing) = inner.get_mut(&gid) {
-            existing.push(app);
-        } else {
-            let apps = vec![ app ];
-            inner.insert(gid, apps);
-        }
-    }
-    msg.reply(ctx, "added game").await?;
-    Ok(())
+    // store steam genres and categories as tags, outside of the data lock
+    let tags = match &app {
+        SuggestedGame::Store(store_app) if store_app.source == Source::Steam => {
+            let steam_inner = {
+                let rlock = ctx.data.read().await;
+                rlock.get::<steam::Client>().expect("no global steam::Client").clone()
+            };
+            match store_app.id.parse::<u32>() {
+                Ok(id) => steam_inner.lock().await.app_details(id).await
+                    .map(|d| steam_tags(&d))
+                    .unwrap_or_default(),
+                Err(_) => Vec::new()
+            }
+        },
+        _ => Vec::new(),
+    };
+    Ok((app, tags))
 }
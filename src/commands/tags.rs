@@ -0,0 +1,74 @@
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        Args,
+        CommandResult
+    },
+    model::channel::Message,
+};
+
+use game_randomizer::tags::normalize_tag;
+
+use crate::commands::suggestions::GameSuggestions;
+use crate::commands::permissions::author_is_moderator;
+
+#[group]
+#[only_in(guilds)]
+#[commands(tag, untag)]
+pub struct Tags;
+
+async fn retag(ctx: &Context, msg: &Message, mut args: Args, add: bool) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let id = match args.single::<String>().ok().and_then(|a| a.trim_start_matches('#').parse::<u32>().ok()) {
+        Some(i) => i,
+        None => {
+            msg.reply(ctx, "Please provide a suggestion id like #12 followed by tags.").await?;
+            return Ok(());
+        }
+    };
+    let tags: Vec<String> = args.quoted().iter::<String>()
+        .flatten()
+        .filter_map(|t| normalize_tag(&t))
+        .collect();
+    if tags.is_empty() {
+        msg.reply(ctx, "Please provide at least one tag.").await?;
+        return Ok(());
+    }
+    let moderator = author_is_moderator(ctx, msg).await;
+    let response = {
+        let mut wlock = ctx.data.write().await;
+        let winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+        match winner.get_mut(&gid).and_then(|b| b.get_mut(id)) {
+            Some(s) if s.user() != msg.author.id && !moderator => {
+                "Found suggestion but cannot be tagged by you.".to_string()
+            },
+            Some(s) => {
+                for tag in tags {
+                    if add {
+                        s.tags.insert(tag);
+                    } else {
+                        s.tags.remove(&tag);
+                    }
+                }
+                let current: Vec<&str> = s.tags.iter().map(|t| t.as_str()).collect();
+                format!{"Tags for #{}: {}", id, if current.is_empty() { "none".to_string() } else { current.join(", ") }}
+            },
+            None => "No suggestion with that id.".to_string()
+        }
+    };
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
+
+// ~tag #12 co-op shooter
+#[command]
+async fn tag(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    retag(ctx, msg, args, true).await
+}
+
+// ~untag #12 shooter
+#[command]
+async fn untag(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    retag(ctx, msg, args, false).await
+}
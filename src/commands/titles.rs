@@ -0,0 +1,77 @@
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        Args,
+        CommandResult
+    },
+    model::channel::Message,
+    utils::MessageBuilder
+};
+
+use game_randomizer::titles::{base_title, normalize_title};
+
+use crate::commands::permissions::MODERATOR_CHECK;
+use crate::commands::settings::{guild_settings, update_settings};
+
+#[group]
+#[only_in(guilds)]
+#[commands(alias, unalias, aliases)]
+pub struct Titles;
+
+// ~alias "CS:GO" "Counter-Strike 2"
+#[command]
+#[checks(Moderator)]
+async fn alias(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let (alias, canonical) = match (args.single_quoted::<String>(), args.single_quoted::<String>()) {
+        (Ok(a), Ok(c)) if !normalize_title(&a).is_empty() && !normalize_title(&c).is_empty() => (a, c),
+        _ => {
+            msg.reply(ctx, "Please provide a title and the game it means, ex: alias \"CS:GO\" \"Counter-Strike 2\"").await?;
+            return Ok(());
+        }
+    };
+    let (key, value) = (normalize_title(&alias), base_title(&canonical));
+    if key == value {
+        msg.reply(ctx, "Those titles already match.").await?;
+        return Ok(());
+    }
+    update_settings(ctx, gid, |s| { s.aliases.insert(key, value); }).await;
+    msg.reply(ctx, format!{"{} now counts as {} when finding common games.", alias, canonical}).await?;
+    Ok(())
+}
+
+// ~unalias "CS:GO"
+#[command]
+#[checks(Moderator)]
+async fn unalias(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let key = normalize_title(args.rest());
+    let mut removed = false;
+    update_settings(ctx, gid, |s| removed = s.aliases.remove(&key).is_some()).await;
+    if removed {
+        msg.reply(ctx, "Removed the alias.").await?;
+    } else {
+        msg.reply(ctx, "There is no alias for that title.").await?;
+    }
+    Ok(())
+}
+
+// ~aliases
+#[command]
+async fn aliases(ctx: &Context, msg: &Message) -> CommandResult {
+    let aliases = guild_settings(ctx, msg.guild_id).await.aliases;
+    if aliases.is_empty() {
+        msg.reply(ctx, "There are no title aliases on this server.").await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = aliases.iter()
+        .map(|(alias, canonical)| format!{"{} -> {}", alias, canonical})
+        .collect();
+    let response = MessageBuilder::new()
+        .push_line("Title aliases")
+        .push_codeblock_safe(lines.join("\n"), None)
+        .build();
+    msg.reply(ctx, response).await?;
+    Ok(())
+}
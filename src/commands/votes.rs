@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use serenity::prelude::*;
+use serenity::client::Context;
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        Args,
+        CommandResult
+    },
+    model::{
+        channel::{Message, Reaction, ReactionType},
+        id::{GuildId, MessageId, UserId},
+    }
+};
+
+use game_randomizer::suggestions::Vote;
+
+use crate::commands::suggestions::GameSuggestions;
+use crate::commands::listing::{self, Listing, ListingQuery, SortOrder};
+
+pub const UPVOTE_EMOJI: &str = "👍";
+pub const DOWNVOTE_EMOJI: &str = "👎";
+/// Number of suggestion messages that still accept reaction votes.
+const MAX_TRACKED: usize = 200;
+
+/// Messages showing a single suggestion, which can be voted on with reactions.
+pub struct VoteMessages;
+impl TypeMapKey for VoteMessages {
+    type Value = Arc<Mutex<HashMap<MessageId, (GuildId, u32)>>>;
+}
+
+/// Adds vote reactions to a message about a suggestion and accepts votes
+/// made through them.
+pub async fn track(ctx: &Context, message: &Message, gid: GuildId, id: u32) -> CommandResult {
+    message.react(ctx, ReactionType::Unicode(UPVOTE_EMOJI.to_string())).await?;
+    message.react(ctx, ReactionType::Unicode(DOWNVOTE_EMOJI.to_string())).await?;
+    let tracked = {
+        let rlock = ctx.data.read().await;
+        rlock.get::<VoteMessages>().expect("no vote messages stored").clone()
+    };
+    let mut tracked = tracked.lock().await;
+    tracked.insert(message.id, (gid, id));
+    if tracked.len() > MAX_TRACKED {
+        if let Some(oldest) = tracked.keys().min().copied() {
+            tracked.remove(&oldest);
+        }
+    }
+    Ok(())
+}
+
+/// Sets or clears a user's vote, returning the new score or `None` if the
+/// suggestion no longer exists.
+pub async fn cast(ctx: &Context, gid: GuildId, id: u32, user: UserId, vote: Option<Vote>) -> Option<i32> {
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+    let suggestion = winner.get_mut(&gid)?.get_mut(id)?;
+    suggestion.vote(user, vote);
+    Some(suggestion.score())
+}
+
+/// Applies a vote reaction being added or removed on a tracked message.
+pub async fn handle_reaction(ctx: &Context, reaction: &Reaction, added: bool) {
+    let user = match reaction.user_id {
+        Some(u) if u != ctx.cache.current_user_id().await => u,
+        _ => return
+    };
+    let vote = match &reaction.emoji {
+        ReactionType::Unicode(e) if e == UPVOTE_EMOJI => Vote::Up,
+        ReactionType::Unicode(e) if e == DOWNVOTE_EMOJI => Vote::Down,
+        _ => return
+    };
+    let target = {
+        let rlock = ctx.data.read().await;
+        let tracked = rlock.get::<VoteMessages>().expect("no vote messages stored").clone();
+        let tracked = tracked.lock().await;
+        tracked.get(&reaction.message_id).copied()
+    };
+    let (gid, id) = match target {
+        Some(t) => t,
+        None => return
+    };
+    if added {
+        cast(ctx, gid, id, user, Some(vote)).await;
+        return;
+    }
+    // only clear the vote if it was the one being withdrawn
+    let mut wlock = ctx.data.write().await;
+    let winner = wlock.get_mut::<GameSuggestions>().expect("no suggestions write data");
+    if let Some(suggestion) = winner.get_mut(&gid).and_then(|b| b.get_mut(id)) {
+        if suggestion.votes.get(&user) == Some(&vote) {
+            suggestion.vote(user, None);
+        }
+    }
+}
+
+#[group]
+#[only_in(guilds)]
+#[commands(upvote, downvote, unvote, top)]
+pub struct Votes;
+
+async fn vote_command(ctx: &Context, msg: &Message, mut args: Args, vote: Option<Vote>) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let id = match args.single::<String>().ok().and_then(|a| a.trim_start_matches('#').parse::<u32>().ok()) {
+        Some(i) => i,
+        None => {
+            msg.reply(ctx, "Please provide a suggestion id like #12.").await?;
+            return Ok(());
+        }
+    };
+    match cast(ctx, gid, id, msg.author.id, vote).await {
+        Some(score) => msg.reply(ctx, format!{"Suggestion #{} now has a score of {}.", id, score}).await?,
+        None => msg.reply(ctx, "No suggestion with that id.").await?,
+    };
+    Ok(())
+}
+
+// ~upvote #12
+#[command]
+#[aliases("up")]
+async fn upvote(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    vote_command(ctx, msg, args, Some(Vote::Up)).await
+}
+
+// ~downvote #12
+#[command]
+#[aliases("down")]
+async fn downvote(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    vote_command(ctx, msg, args, Some(Vote::Down)).await
+}
+
+// ~unvote #12
+#[command]
+async fn unvote(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    vote_command(ctx, msg, args, None).await
+}
+
+// ~top, ranked backlog by vote score
+#[command]
+#[aliases("backlog")]
+async fn top(ctx: &Context, msg: &Message) -> CommandResult {
+    let gid = msg.guild_id.expect("only_in guilds");
+    let query = ListingQuery {
+        sort: SortOrder::Votes,
+        ..ListingQuery::default()
+    };
+    listing::post(ctx, msg, Listing { guild: gid, query, page: 0 }).await
+}
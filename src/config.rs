@@ -1,68 +1,185 @@
+use std::env;
 use std::fs;
 use std::fs::File;
+use std::io;
 use std::path::Path;
+use clap::{App as ClapApp, Arg, ArgMatches, crate_version};
 use serenity::prelude::*;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use tracing::{info, warn, Level};
 
 use crate::commands::{
     suggestions::GameSuggestions,
-    players::PlayerContainer
+    players::PlayerContainer,
+    settings::GuildSettingsContainer,
+    cleanup::DepartedGuilds,
+    gamenights::GameNightContainer
 };
 
-#[derive(Deserialize)]
+const DEFAULT_CONFIG: &str = "./config.toml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unable to read config file `{path}`: {source}")]
+    Read { path: String, source: io::Error },
+    #[error("malformed config file `{path}`: {source}")]
+    Parse { path: String, source: toml::de::Error },
+    #[error("missing config key `{key}`, set it in the config file or with the {env} environment variable")]
+    Missing { key: &'static str, env: &'static str },
+    #[error("invalid value `{value}` for config key `{key}`, expected {expected}")]
+    Invalid { key: &'static str, value: String, expected: &'static str },
+}
+
+/// Values as they appear in the config file, all of which may be
+/// supplied or overridden from the environment or command line instead.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    discord: Option<String>,
+    steam: Option<String>,
+    steam_api: Option<String>,
+    storage: Option<String>,
+    log_level: Option<String>,
+}
+
+/// Where steam requests are sent.
+pub enum SteamBackend {
+    Steam,
+    /// Another server with the same endpoints, by base url.
+    Url(String),
+    /// A stub server started from a fake's json fixture.
+    Stub(String),
+}
+
 pub struct Config {
     pub discord: String,
     pub steam: String,
-    pub storage: String
+    pub steam_backend: SteamBackend,
+    pub storage: String,
+    pub log_level: Level,
 }
 impl Config {
-    pub fn from_file(path: &str) -> Result<Self, ()> {
+    /// Builds the config from command line flags, environment variables and
+    /// the config file, in that order of precedence.
+    pub fn from_args() -> Result<Self, ConfigError> {
+        Config::from_matches(&Config::app().get_matches(), &|key| env::var(key).ok())
+    }
+    fn app() -> ClapApp<'static, 'static> {
+        ClapApp::new("game_randomizer")
+            .version(crate_version!())
+            .about("Discord bot for suggesting and picking games to play")
+            .arg(Arg::with_name("config")
+                .long("config")
+                .short("c")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Path to the config file [default: ./config.toml]"))
+            .arg(Arg::with_name("storage")
+                .long("storage")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Directory used to save suggestions and players [env: STORAGE_DIR]"))
+            .arg(Arg::with_name("steam-api")
+                .long("steam-api")
+                .value_name("URL")
+                .takes_value(true)
+                .help("Send steam requests to another server [env: STEAM_API_URL]"))
+            .arg(Arg::with_name("steam-stub")
+                .long("steam-stub")
+                .value_name("FIXTURE")
+                .takes_value(true)
+                .conflicts_with("steam-api")
+                .help("Serve steam from a json fixture on a local stub server"))
+            .arg(Arg::with_name("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .takes_value(true)
+                .help("One of error, warn, info, debug or trace [default: info]"))
+    }
+    /// Builds the config from parsed flags, reading variables through `var`.
+    fn from_matches(matches: &ArgMatches, var: &dyn Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        // only an explicitly requested config file is required to exist
+        let file = match matches.value_of("config") {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG).exists() => Config::from_file(DEFAULT_CONFIG)?,
+            None => ConfigFile::default(),
+        };
+        let discord = var("DISCORD_TOKEN")
+            .or(file.discord)
+            .ok_or(ConfigError::Missing { key: "discord", env: "DISCORD_TOKEN" })?;
+        let steam_backend = match matches.value_of("steam-stub") {
+            Some(fixture) => SteamBackend::Stub(fixture.to_string()),
+            None => match matches.value_of("steam-api").map(String::from)
+                .or_else(|| var("STEAM_API_URL"))
+                .or(file.steam_api) {
+                Some(url) => SteamBackend::Url(url),
+                None => SteamBackend::Steam,
+            }
+        };
+        // the stub doesn't check keys
+        let steam = var("STEAM_API_KEY")
+            .or(file.steam)
+            .or_else(|| matches!(steam_backend, SteamBackend::Stub(_)).then(|| "stub".to_string()))
+            .ok_or(ConfigError::Missing { key: "steam", env: "STEAM_API_KEY" })?;
+        let storage = matches.value_of("storage").map(String::from)
+            .or_else(|| var("STORAGE_DIR"))
+            .or(file.storage)
+            .ok_or(ConfigError::Missing { key: "storage", env: "STORAGE_DIR" })?;
+        let log_level = match matches.value_of("log-level").map(String::from).or(file.log_level) {
+            Some(level) => match level.parse::<Level>() {
+                Ok(l) => l,
+                Err(_) => return Err(ConfigError::Invalid {
+                    key: "log_level",
+                    value: level,
+                    expected: "one of error, warn, info, debug or trace"
+                })
+            },
+            None => Level::INFO
+        };
+        Ok(Config { discord, steam, steam_backend, storage, log_level })
+    }
+    fn from_file(path: &str) -> Result<ConfigFile, ConfigError> {
         let data = match fs::read_to_string(path) {
             Ok(s) => s,
-            Err(_) => return Err(())
+            Err(source) => return Err(ConfigError::Read { path: path.to_string(), source })
         };
-        let config: Config = match toml::from_str(&data) {
-            Ok(c) => c,
-            Err(_) => return Err(())
-        };
-        Ok(config)
+        match toml::from_str(&data) {
+            Ok(c) => Ok(c),
+            Err(source) => Err(ConfigError::Parse { path: path.to_string(), source })
+        }
     }
     pub fn load_suggestions(&self) -> Option<<GameSuggestions as TypeMapKey>::Value> {
-        let storage = Path::new(&self.storage);
-        match File::open(storage.join("suggestions.json")) {
-            Err(_) => {
-                println!{"Failure opening suggestions file"};
-                return None;
-            },
-            Ok(p) => {
-                match serde_json::from_reader(p) {
-                    Err(_) => {
-                        println!{"Failure deserializing suggestions file"};
-                        return None;
-                    },
-                    Ok(s) => {
-                        println!{"Opened previous suggestions file!"}
-                        Some(s)
-                    }
-                }
-            }
-        }
+        self.load("suggestions")
     }
     pub fn load_players(&self) -> Option<<PlayerContainer as TypeMapKey>::Value> {
+        self.load("players")
+    }
+    pub fn load_settings(&self) -> Option<<GuildSettingsContainer as TypeMapKey>::Value> {
+        self.load("settings")
+    }
+    pub fn load_departed(&self) -> Option<<DepartedGuilds as TypeMapKey>::Value> {
+        self.load("departed")
+    }
+    pub fn load_gamenights(&self) -> Option<<GameNightContainer as TypeMapKey>::Value> {
+        self.load("gamenights")
+    }
+    fn load<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
         let storage = Path::new(&self.storage);
-        match File::open(storage.join("players.json")) {
+        match File::open(storage.join(format!{"{}.json", name})) {
             Err(_) => {
-                println!{"Failure opening players file"};
-                return None;
+                warn!("Failure opening {} file", name);
+                None
             },
             Ok(p) => {
                 match serde_json::from_reader(p) {
                     Err(_) => {
-                        println!{"Failure deserializing players file"};
-                        return None;
+                        warn!("Failure deserializing {} file", name);
+                        None
                     },
                     Ok(s) => {
-                        println!{"Opened previous players file!"}
+                        info!("Opened previous {} file!", name);
                         Some(s)
                     }
                 }
@@ -73,3 +190,67 @@ impl Config {
 impl TypeMapKey for Config {
     type Value = Config;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Writes a config file only this test reads, returning its path.
+    fn config_file(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!{"game_randomizer_{}_{}.toml", name, std::process::id()});
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn config(args: &[&str], vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let matches = Config::app().get_matches_from(["game_randomizer"].iter().chain(args));
+        Config::from_matches(&matches, &|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn flags_override_env_which_overrides_file() {
+        let path = config_file("precedence", r#"
+            discord = "file-token"
+            steam = "file-key"
+            storage = "file-dir"
+            steam_api = "http://file"
+            log_level = "warn"
+        "#);
+        let from_file = config(&["-c", &path], &[]).unwrap();
+        assert_eq!(from_file.discord, "file-token");
+        assert_eq!(from_file.steam, "file-key");
+        assert_eq!(from_file.storage, "file-dir");
+        assert!(matches!(from_file.steam_backend, SteamBackend::Url(ref u) if u == "http://file"));
+        assert_eq!(from_file.log_level, Level::WARN);
+
+        let vars = [("DISCORD_TOKEN", "env-token"), ("STORAGE_DIR", "env-dir"), ("STEAM_API_URL", "http://env")];
+        let from_env = config(&["-c", &path], &vars).unwrap();
+        assert_eq!(from_env.discord, "env-token");
+        assert_eq!(from_env.storage, "env-dir");
+        assert!(matches!(from_env.steam_backend, SteamBackend::Url(ref u) if u == "http://env"));
+
+        let args = ["-c", &path, "--storage", "flag-dir", "--steam-api", "http://flag", "--log-level", "debug"];
+        let from_flags = config(&args, &vars).unwrap();
+        assert_eq!(from_flags.storage, "flag-dir");
+        assert!(matches!(from_flags.steam_backend, SteamBackend::Url(ref u) if u == "http://flag"));
+        assert_eq!(from_flags.log_level, Level::DEBUG);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_values() {
+        let path = config_file("missing", "discord = \"token\"\nstorage = \"dir\"\n");
+        assert!(matches!(config(&["-c", &path], &[]), Err(ConfigError::Missing { key: "steam", .. })));
+        // the stub doesn't need a key
+        let stub = config(&["-c", &path, "--steam-stub", "fixture.json"], &[]).unwrap();
+        assert!(matches!(stub.steam_backend, SteamBackend::Stub(_)));
+        assert!(matches!(
+            config(&["-c", &path, "--log-level", "loud"], &[("STEAM_API_KEY", "key")]),
+            Err(ConfigError::Invalid { key: "log_level", .. })
+        ));
+        fs::remove_file(path).unwrap();
+        assert!(matches!(config(&["-c", "/nonexistent/config.toml"], &[]), Err(ConfigError::Read { .. })));
+    }
+}
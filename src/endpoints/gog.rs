@@ -0,0 +1,64 @@
+/// Game titles from a GOG Galaxy library export. Exports are CSV with a
+/// header row naming a `title` or `name` column, separated by commas,
+/// semicolons or tabs depending on the exporter. Galaxy can't export its
+/// library itself, the supported exporter is the GOG Galaxy Export Script
+/// (https://github.com/AB1908/GOG-Galaxy-Export-Script), whose
+/// `gameDetails.csv` this reads. Galaxy's own `galaxy-2.0.db` is not read.
+pub fn parse_export(data: &str) -> Result<Vec<String>, String> {
+    let data = data.trim_start_matches('\u{feff}');
+    let header = data.lines().next().unwrap_or_default();
+    let delimiter = [b'\t', b';', b',']
+        .iter()
+        .copied()
+        .max_by_key(|d| header.bytes().filter(|b| b == d).count())
+        .filter(|d| header.as_bytes().contains(d))
+        .unwrap_or(b',');
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(data.as_bytes());
+    let headers = reader.headers().map_err(|_| "The file is not a CSV export.".to_string())?;
+    let column = match headers.iter().position(|h| h.trim().eq_ignore_ascii_case("title"))
+        .or_else(|| headers.iter().position(|h| h.trim().eq_ignore_ascii_case("name"))) {
+        Some(c) => c,
+        None => return Err("The export has no 'title' column.".to_string())
+    };
+    let mut titles: Vec<String> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!{"Could not read the export: {}", e})?;
+        if let Some(title) = record.get(column).map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if !titles.iter().any(|t| t == title) {
+                titles.push(title.to_string());
+            }
+        }
+    }
+    Ok(titles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comma_export() {
+        let titles = parse_export(include_str!("../../tests/fixtures/gog_galaxy.csv")).unwrap();
+        assert_eq!(titles, vec![
+            "The Witcher 3: Wild Hunt - Game of the Year Edition",
+            "Rocket League®",
+            "Stardew Valley",
+            "Baldur's Gate 3",
+        ]);
+    }
+
+    #[test]
+    fn parses_tab_export_and_skips_blanks_and_duplicates() {
+        let titles = parse_export(include_str!("../../tests/fixtures/gog_galaxy_tabs.csv")).unwrap();
+        assert_eq!(titles, vec!["Disco Elysium", "Hades"]);
+    }
+
+    #[test]
+    fn rejects_exports_without_titles() {
+        assert!(parse_export("id,platform\n1,gog\n").is_err());
+        assert!(parse_export("").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use serde_json::Value;
+
+/// Game titles from an itch.io library, either the api's owned keys
+/// response or a plain list of games. Soundtracks, tools and other
+/// non-game purchases are skipped.
+pub fn parse_library(data: &str) -> Result<Vec<String>, String> {
+    let value: Value = serde_json::from_str(data.trim_start_matches('\u{feff}'))
+        .map_err(|_| "The file is not a JSON library export.".to_string())?;
+    let entries = match &value {
+        Value::Array(entries) => entries,
+        Value::Object(map) => match map.get("owned_keys").or_else(|| map.get("games")) {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err("The export has no 'owned_keys' or 'games' list.".to_string())
+        },
+        _ => return Err("The export has no list of games.".to_string())
+    };
+    let mut titles: Vec<String> = Vec::new();
+    for entry in entries {
+        let game = entry.get("game").unwrap_or(entry);
+        let is_game = match game.get("classification").and_then(|c| c.as_str()) {
+            Some(c) => c == "game",
+            None => true
+        };
+        let title = game.get("title").and_then(|t| t.as_str()).map(|t| t.trim()).unwrap_or_default();
+        if is_game && !title.is_empty() && !titles.iter().any(|t| t == title) {
+            titles.push(title.to_string());
+        }
+    }
+    Ok(titles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_owned_keys() {
+        let titles = parse_library(include_str!("../../tests/fixtures/itch_owned_keys.json")).unwrap();
+        assert_eq!(titles, vec!["Celeste", "A Short Hike"]);
+    }
+
+    #[test]
+    fn parses_game_lists() {
+        let titles = parse_library(include_str!("../../tests/fixtures/itch_games.json")).unwrap();
+        assert_eq!(titles, vec!["Baba Is You", "Wandersong"]);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse_library("title,platform").is_err());
+        assert!(parse_library(r#"{"page": 1}"#).is_err());
+    }
+}
@@ -1,3 +1,109 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use serenity::async_trait;
+use serenity::prelude::*;
+
 pub mod steam;
+pub mod gog;
+pub mod itch;
+
+/// A store that games can be looked up in and owned through.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash,PartialOrd,Ord,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Steam,
+}
+impl Source {
+    pub fn url(self, id: &str) -> String {
+        match self {
+            Source::Steam => steam::store_url(id),
+        }
+    }
+}
+impl FromStr for Source {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "steam" => Ok(Source::Steam),
+            _ => Err(format!{"Unknown store '{}'. Try 'steam'.", s})
+        }
+    }
+}
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Steam => write!(f, "Steam"),
+        }
+    }
+}
+
+/// A game as listed by one of the sources, ids are whatever the source uses.
+#[derive(Clone,Debug,Deserialize,Serialize)]
+#[serde(from = "StoredGame")]
+pub struct StoreGame {
+    pub source: Source,
+    pub id: String,
+    pub name: String,
+}
+impl StoreGame {
+    pub fn url(&self) -> String {
+        self.source.url(&self.id)
+    }
+}
+impl PartialEq for StoreGame {
+    fn eq(&self, other: &StoreGame) -> bool {
+        self.source == other.source && self.id == other.id
+    }
+}
+impl fmt::Display for StoreGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!{f, "[{}]({})", self.name, self.url()}
+    }
+}
+
+// games used to be steam apps with numeric ids
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredGame {
+    Current {
+        source: Source,
+        id: String,
+        name: String
+    },
+    Legacy(steam::App),
+}
+impl From<StoredGame> for StoreGame {
+    fn from(stored: StoredGame) -> Self {
+        match stored {
+            StoredGame::Current { source, id, name } => StoreGame { source, id, name },
+            StoredGame::Legacy(app) => app.into(),
+        }
+    }
+}
+
+/// What the bot needs from a game store: looking games up and listing the
+/// games an account owns. Store pages are linked through `Source::url` so
+/// stored games can link to them without a client.
+#[async_trait]
+pub trait GameSource: Send + Sync {
+    fn source(&self) -> Source;
+    async fn game_by_id(&self, id: &str) -> Result<StoreGame, ()>;
+    async fn game_by_name(&self, name: &str) -> Result<StoreGame, ()>;
+    async fn owned_games(&self, account: &str) -> Result<Vec<StoreGame>, ()>;
+    /// Looks a game up by id, then by name.
+    async fn lookup(&self, query: &str) -> Result<StoreGame, ()> {
+        match self.game_by_id(query).await {
+            Ok(game) => Ok(game),
+            Err(_) => self.game_by_name(query).await
+        }
+    }
+}
 
-pub use self::steam::{Client, App, Apps};
+/// Every available game source, by the source it serves.
+pub struct GameSources;
+impl TypeMapKey for GameSources {
+    type Value = HashMap<Source, Arc<Mutex<dyn GameSource>>>;
+}
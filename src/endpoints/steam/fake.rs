@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::fs;
+use rsteam::SteamID;
+use serde::{Serialize, Deserialize};
+use serenity::async_trait;
+
+use super::{App, AppDetails, OwnedGame, PlayerSummary, SteamApi, Visibility};
+
+/// A steam user known to the fake.
+#[derive(Deserialize,Serialize)]
+pub struct FakePlayer {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Their games, `None` when the library is private.
+    #[serde(default)]
+    pub games: Option<Vec<OwnedGame>>,
+}
+
+/// Steam served from memory, for tests and running without steam. Read
+/// from a json fixture of the same shape.
+#[derive(Default,Deserialize,Serialize)]
+#[serde(default)]
+pub struct FakeSteam {
+    pub apps: Vec<App>,
+    /// Steam ids by vanity name.
+    pub vanity: HashMap<String, u64>,
+    pub players: Vec<FakePlayer>,
+    pub details: HashMap<u32, AppDetails>,
+}
+impl FakeSteam {
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        serde_json::from_str(data).map_err(|why| format!{"Malformed steam fixture: {}", why})
+    }
+    pub fn from_fixture(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(data) => FakeSteam::from_json(&data),
+            Err(why) => Err(format!{"Unable to read steam fixture `{}`: {}", path, why})
+        }
+    }
+    fn player(&self, id: &SteamID) -> Option<&FakePlayer> {
+        let id: u64 = id.into();
+        self.players.iter().find(|p| p.id == id)
+    }
+}
+
+#[async_trait]
+impl SteamApi for FakeSteam {
+    async fn app_list(&self) -> Result<Vec<App>, ()> {
+        Ok(self.apps.clone())
+    }
+    async fn resolve_vanity_user(&self, name: &str) -> Result<SteamID, ()> {
+        self.vanity.get(name).map(|id| SteamID::from(*id)).ok_or(())
+    }
+    async fn owned_games(&self, user: &SteamID) -> Result<Vec<OwnedGame>, ()> {
+        Ok(self.player(user).and_then(|p| p.games.clone()).unwrap_or_default())
+    }
+    async fn player_summaries(&self, users: &[SteamID]) -> Result<Vec<PlayerSummary>, ()> {
+        Ok(users.iter()
+            .filter_map(|u| self.player(u))
+            .map(|p| PlayerSummary {
+                id: p.id.into(),
+                name: p.name.clone(),
+                url: format!{"https://steamcommunity.com/profiles/{}/", p.id},
+                visibility: p.visibility
+            })
+            .collect())
+    }
+    async fn app_details(&self, id: u32) -> Result<AppDetails, ()> {
+        self.details.get(&id).cloned().ok_or(())
+    }
+}
@@ -0,0 +1,400 @@
+use std::sync::Arc;
+use std::fmt;
+use std::str::FromStr;
+use rsteam::SteamID;
+use serenity::async_trait;
+use serenity::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::endpoints::{GameSource, Source, StoreGame};
+
+pub mod web;
+pub mod fake;
+pub mod stub;
+
+pub use self::web::WebApi;
+pub use self::fake::FakeSteam;
+
+pub fn store_url(id: &str) -> String {
+    format!("https://store.steampowered.com/app/{}/", id)
+}
+
+#[derive(Clone,Debug,PartialEq,Deserialize,Serialize)]
+pub struct App {
+    pub id: u32,
+    pub name: String,
+}
+impl fmt::Display for App {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!{f, "[{}]({})", self.name, store_url(&self.id.to_string())}
+    }
+}
+impl From<App> for StoreGame {
+    fn from(app: App) -> Self {
+        StoreGame {
+            source: Source::Steam,
+            id: app.id.to_string(),
+            name: app.name
+        }
+    }
+}
+#[derive(Deserialize,Serialize)]
+pub struct Apps (Vec<App>);
+impl Apps {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn find_by_id(&self, id: u32) -> Result<App, ()> {
+        match self.0.iter().find(|a| a.id == id) {
+            Some(app) => Ok( App{
+                                id: app.id,
+                                name: app.name.clone()
+                        }),
+            None => Err( () ), // TODO: ERROR NOT FOUND
+        }
+    }
+    pub fn find_by_name(&self, name: &str) -> Result<App, ()> {
+        match self.0.iter().find(|a| a.name == name) {
+            Some(app) => Ok( App{
+                                id: app.id,
+                                name: app.name.clone()
+                        }),
+            None => Err( () ), // TODO: ERROR NOT FOUND
+        }
+    }
+    /// Apps whose name contains the query ignoring case, those starting
+    /// with it first, at most `limit` of them.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&App> {
+        let query = query.trim().to_lowercase();
+        let mut found: Vec<(bool, &App)> = self.0.iter()
+            .filter_map(|a| {
+                let name = a.name.to_lowercase();
+                name.find(&query).map(|at| (at != 0, a))
+            })
+            .collect();
+        found.sort_by_key(|(later, a)| (*later, a.name.len()));
+        found.into_iter().take(limit).map(|(_, a)| a).collect()
+    }
+}
+impl Default for Apps {
+    fn default() -> Self {
+        Apps( Vec::with_capacity(5000) )
+    }
+}
+impl From<Vec<App>> for Apps {
+    fn from(apps: Vec<App>) -> Self {
+        Apps(apps)
+    }
+}
+impl fmt::Display for Apps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!{f, "Application List"}?;
+        for app in self.0.iter() {
+            writeln!{f, "{}", app}?;
+        }
+        Ok(())
+    }
+}
+
+/// Steam id of the first individual account, 32-bit account ids are
+/// offsets from it.
+const INDIVIDUAL_BASE: u64 = 76561197960265728;
+
+/// A steam user as someone wrote it: either an id or a vanity name that
+/// still has to be resolved through the api.
+#[derive(Debug,PartialEq)]
+pub enum UserRef {
+    Id(SteamID),
+    Vanity(String),
+}
+impl UserRef {
+    fn account(account_id: u64) -> Result<Self, String> {
+        if account_id > u32::MAX as u64 {
+            return Err(format!{"Account number {} is too large for a steam id.", account_id});
+        }
+        Ok(UserRef::Id((INDIVIDUAL_BASE + account_id).into()))
+    }
+    fn profile_url(path: &str) -> Result<Self, String> {
+        let mut parts = path.split('/').filter(|p| !p.is_empty());
+        match (parts.next(), parts.next()) {
+            (Some("id"), Some(name)) => name.parse::<UserRef>(),
+            (Some("profiles"), Some(id)) => match id.parse::<u64>() {
+                Ok(i) => UserRef::id64(i),
+                Err(_) => Err(format!{"'{}' in the profile link is not a steam id.", id})
+            },
+            _ => Err("Profile links should look like steamcommunity.com/id/name or steamcommunity.com/profiles/7656...".to_string())
+        }
+    }
+    fn id64(id: u64) -> Result<Self, String> {
+        if (INDIVIDUAL_BASE..=INDIVIDUAL_BASE + u32::MAX as u64).contains(&id) {
+            Ok(UserRef::Id(id.into()))
+        } else {
+            Err(format!{"{} is not the steam id of a user.", id})
+        }
+    }
+}
+/// Parses 64-bit ids, `STEAM_0:1:1234`, `[U:1:1234]`, community profile
+/// links and vanity names.
+impl FromStr for UserRef {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches('<').trim_end_matches('>');
+        let link = s.trim_start_matches("https://").trim_start_matches("http://").trim_start_matches("www.");
+        if let Some(path) = link.strip_prefix("steamcommunity.com/") {
+            return UserRef::profile_url(path);
+        }
+        if s.get(..6).is_some_and(|p| p.eq_ignore_ascii_case("steam_")) {
+            let parts: Vec<&str> = s[6..].split(':').collect();
+            return match parts.as_slice() {
+                [_, y @ ("0" | "1"), z] => match z.parse::<u64>() {
+                    Ok(z) => match z.checked_mul(2).and_then(|z| z.checked_add(y.parse::<u64>().expect("0 or 1"))) {
+                        Some(account_id) => UserRef::account(account_id),
+                        None => Err(format!{"'{}' is too large for a steam id.", s})
+                    },
+                    Err(_) => Err(format!{"'{}' is not a valid STEAM_X:Y:Z id.", s})
+                },
+                _ => Err(format!{"'{}' is not a valid STEAM_X:Y:Z id, ex: STEAM_0:1:1234", s})
+            };
+        }
+        if s.starts_with('[') || s.starts_with("U:") {
+            let inner = s.trim_start_matches('[').trim_end_matches(']');
+            let parts: Vec<&str> = inner.split(':').collect();
+            return match parts.as_slice() {
+                ["U", "1", w] => match w.parse::<u64>() {
+                    Ok(w) => UserRef::account(w),
+                    Err(_) => Err(format!{"'{}' is not a valid [U:1:X] id.", s})
+                },
+                _ => Err(format!{"'{}' is not a valid user id, ex: [U:1:1234]", s})
+            };
+        }
+        // vanity names can be numeric too, only full length ids are taken as ids
+        if s.len() == 17 && s.chars().all(|c| c.is_ascii_digit()) {
+            return UserRef::id64(s.parse::<u64>().map_err(|_| format!{"{} is not a steam id.", s})?);
+        }
+        let valid_vanity = (2..=32).contains(&s.len())
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid_vanity {
+            Ok(UserRef::Vanity(s.to_string()))
+        } else {
+            Err(format!{"'{}' is not a steam id, profile link or vanity name.", s})
+        }
+    }
+}
+
+/// A genre or category entry from the store's app details.
+#[derive(Clone,Deserialize,Serialize)]
+pub struct Description {
+    pub description: String,
+}
+
+/// The parts of the store's app details the bot makes use of.
+#[derive(Clone,Default,Deserialize,Serialize)]
+pub struct AppDetails {
+    #[serde(default)]
+    pub genres: Vec<Description>,
+    #[serde(default)]
+    pub categories: Vec<Description>,
+}
+
+/// A game in a user's library. Names are missing for apps that have left
+/// the store.
+#[derive(Clone,Debug,Deserialize,Serialize)]
+pub struct OwnedGame {
+    pub appid: u32,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Who can see a steam profile, and with it the games it owns.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Private,
+    FriendsOnly,
+    #[default]
+    Public,
+}
+
+/// The public parts of a steam profile.
+#[derive(Clone,Debug)]
+pub struct PlayerSummary {
+    pub id: SteamID,
+    pub name: String,
+    pub url: String,
+    pub visibility: Visibility,
+}
+
+/// The steam web and store api calls the bot makes, so they can be served
+/// by a fake or a local stub instead of steam.
+#[async_trait]
+pub trait SteamApi: Send + Sync {
+    async fn app_list(&self) -> Result<Vec<App>, ()>;
+    async fn resolve_vanity_user(&self, name: &str) -> Result<SteamID, ()>;
+    /// Private libraries are empty rather than an error, as steam has it.
+    async fn owned_games(&self, user: &SteamID) -> Result<Vec<OwnedGame>, ()>;
+    async fn player_summaries(&self, users: &[SteamID]) -> Result<Vec<PlayerSummary>, ()>;
+    async fn app_details(&self, id: u32) -> Result<AppDetails, ()>;
+}
+
+/// Steam games and users, with the app list kept around for lookups.
+pub struct Client {
+    api: Box<dyn SteamApi>,
+    apps: Apps,
+}
+impl Client {
+    pub fn new<A: SteamApi + 'static>(api: A) -> Self {
+        Client {
+            api: Box::new(api),
+            apps: Apps::default()
+        }
+    }
+    pub fn with_api_key(key: &str) -> Self {
+        Client::new(WebApi::with_api_key(key))
+    }
+    pub async fn fill_app_list(&mut self) -> Result<(), ()> {
+        self.apps = self.api.app_list().await?.into();
+        Ok(())
+    }
+    pub async fn game_by_id(&self, id:u32) -> Result<App, ()> {
+        if self.apps.is_empty() {
+            return Err(()); // TODO: ERROR LIST EMPTY
+        }
+        self.apps.find_by_id(id)
+    }
+    pub async fn game_by_name(&self, name:&str) -> Result<App, ()> {
+        if self.apps.is_empty() {
+            return Err(()); // TODO: ERROR LIST EMPTY
+        }
+        self.apps.find_by_name(name)
+    }
+    /// Apps to offer while someone types a title.
+    pub fn search_apps(&self, query: &str, limit: usize) -> Vec<App> {
+        self.apps.search(query, limit).into_iter().cloned().collect()
+    }
+    pub async fn resolve_vanity_user(&self, user:&str) -> Result<SteamID, ()> {
+        self.api.resolve_vanity_user(user).await
+    }
+    pub async fn user_owned_games(&self, user: &SteamID) -> Result<Vec<OwnedGame>, ()> {
+        self.api.owned_games(user).await
+    }
+    pub async fn player_summaries(&self, users: &[SteamID]) -> Result<Vec<PlayerSummary>, ()> {
+        self.api.player_summaries(users).await
+    }
+    /// Looks up an app's genres and categories from the steam store.
+    pub async fn app_details(&self, id: u32) -> Result<AppDetails, ()> {
+        self.api.app_details(id).await
+    }
+}
+
+#[async_trait]
+impl GameSource for Client {
+    fn source(&self) -> Source {
+        Source::Steam
+    }
+    async fn game_by_id(&self, id: &str) -> Result<StoreGame, ()> {
+        match id.parse::<u32>() {
+            Ok(id) => Client::game_by_id(self, id).await.map(StoreGame::from),
+            Err(_) => Err(())
+        }
+    }
+    async fn game_by_name(&self, name: &str) -> Result<StoreGame, ()> {
+        Client::game_by_name(self, name).await.map(StoreGame::from)
+    }
+    async fn owned_games(&self, account: &str) -> Result<Vec<StoreGame>, ()> {
+        let user: SteamID = match account.parse::<u64>() {
+            Ok(id) => id.into(),
+            Err(_) => return Err(())
+        };
+        let owned = self.user_owned_games(&user).await?;
+        let mut games = Vec::with_capacity(owned.len());
+        for game in owned {
+            let name = match game.name {
+                Some(n) => n,
+                None => match self.apps.find_by_id(game.appid) {
+                    Ok(app) => app.name,
+                    Err(()) => String::from("** NNF **"),
+                }
+            };
+            games.push(StoreGame { source: Source::Steam, id: game.appid.to_string(), name });
+        }
+        Ok(games)
+    }
+}
+
+impl TypeMapKey for Client {
+    type Value = Arc<Mutex<Client>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(id: u64) -> Result<UserRef, String> {
+        Ok(UserRef::Id(id.into()))
+    }
+
+    #[test]
+    fn parses_64_bit_ids() {
+        assert_eq!("76561197960287930".parse::<UserRef>(), id(76561197960287930));
+        assert!("76561197960265727".parse::<UserRef>().is_err());
+        assert!("99999999999999999".parse::<UserRef>().is_err());
+    }
+
+    #[test]
+    fn parses_steam2_ids() {
+        assert_eq!("STEAM_0:0:11101".parse::<UserRef>(), id(76561197960287930));
+        assert_eq!("steam_1:1:11101".parse::<UserRef>(), id(76561197960287931));
+        assert!("STEAM_0:2:11101".parse::<UserRef>().is_err());
+        assert!("STEAM_0:0".parse::<UserRef>().is_err());
+        assert!("STEAM_0:0:abc".parse::<UserRef>().is_err());
+        assert!("STEAM_0:1:18446744073709551615".parse::<UserRef>().is_err());
+        assert!("STEAM_0:1:2147483648".parse::<UserRef>().is_err());
+    }
+
+    #[test]
+    fn parses_steam3_ids() {
+        assert_eq!("[U:1:22202]".parse::<UserRef>(), id(76561197960287930));
+        assert_eq!("U:1:22202".parse::<UserRef>(), id(76561197960287930));
+        assert!("[G:1:22202]".parse::<UserRef>().is_err());
+        assert!("[U:1:99999999999]".parse::<UserRef>().is_err());
+    }
+
+    #[test]
+    fn parses_profile_links() {
+        assert_eq!("https://steamcommunity.com/profiles/76561197960287930/".parse::<UserRef>(), id(76561197960287930));
+        assert_eq!("<steamcommunity.com/profiles/76561197960287930>".parse::<UserRef>(), id(76561197960287930));
+        assert_eq!("https://steamcommunity.com/id/gabelogannewell/".parse::<UserRef>(), Ok(UserRef::Vanity("gabelogannewell".to_string())));
+        assert_eq!("http://www.steamcommunity.com/id/foo".parse::<UserRef>(), Ok(UserRef::Vanity("foo".to_string())));
+        assert!("https://steamcommunity.com/groups/foo".parse::<UserRef>().is_err());
+        assert!("https://steamcommunity.com/profiles/foo".parse::<UserRef>().is_err());
+    }
+
+    #[test]
+    fn searches_apps_by_name() {
+        let apps = Apps::from(vec![
+            App { id: 1, name: "Portal 2".to_string() },
+            App { id: 2, name: "Portal".to_string() },
+            App { id: 3, name: "Aperture: Portal Stories".to_string() },
+            App { id: 4, name: "Valheim".to_string() },
+        ]);
+        let ids = |found: Vec<&App>| found.iter().map(|a| a.id).collect::<Vec<_>>();
+        assert_eq!(ids(apps.search("portal", 25)), vec![2, 1, 3]);
+        assert_eq!(ids(apps.search("PORTAL", 2)), vec![2, 1]);
+        assert!(apps.search("half-life", 25).is_empty());
+    }
+
+    #[test]
+    fn parses_vanity_names() {
+        assert_eq!("foo_bar-1".parse::<UserRef>(), Ok(UserRef::Vanity("foo_bar-1".to_string())));
+        assert_eq!("12345".parse::<UserRef>(), Ok(UserRef::Vanity("12345".to_string())));
+        assert!("not a name".parse::<UserRef>().is_err());
+        assert!("x".parse::<UserRef>().is_err());
+    }
+
+    #[test]
+    fn rejects_multibyte_input() {
+        assert!("aéééé".parse::<UserRef>().is_err());
+        assert!("steaméé".parse::<UserRef>().is_err());
+        assert!("[U:1:é]".parse::<UserRef>().is_err());
+    }
+}
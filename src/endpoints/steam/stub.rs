@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use rsteam::SteamID;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+use super::{FakeSteam, SteamApi};
+use super::web::{
+    AppDetailsResponse, AppList, AppListResponse, ListedApp, OwnedGamesResponse, Player,
+    PlayersResponse, VanityResponse, Wrapped,
+    APP_DETAILS_PATH, APP_LIST_PATH, OWNED_GAMES_PATH, SUMMARIES_PATH, VANITY_PATH,
+};
+
+/// Largest request head read before giving up on a connection.
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Serves a fake's data on a local port in the layout of the steam web api
+/// and store, so `WebApi::with_base_url` can be pointed at it. Returns the
+/// address it listens on.
+pub async fn start(steam: FakeSteam) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    info!("Steam stub listening on http://{}", addr);
+    tokio::spawn(serve(listener, Arc::new(steam)));
+    Ok(addr)
+}
+
+/// Answers requests on the listener until it fails.
+pub async fn serve(listener: TcpListener, steam: Arc<FakeSteam>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let steam = steam.clone();
+        tokio::spawn(async move {
+            if let Err(why) = handle(stream, &steam).await {
+                debug!("Steam stub connection failed: {}", why);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, steam: &FakeSteam) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || head.len() > MAX_REQUEST {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let target = head.lines().next()
+        .and_then(|line| line.strip_prefix("GET "))
+        .and_then(|rest| rest.split(' ').next())
+        .unwrap_or("");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let response = match route(steam, path, &query_params(query)).await {
+        Some(body) => format!{
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(), body
+        },
+        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// The json steam would answer a request with, or `None` for paths it
+/// doesn't serve.
+async fn route(steam: &FakeSteam, path: &str, query: &HashMap<String, String>) -> Option<String> {
+    let param = |name: &str| query.get(name).map(String::as_str).unwrap_or("");
+    let body = match path {
+        APP_LIST_PATH => {
+            let apps = steam.app_list().await.unwrap_or_default().into_iter()
+                .map(|a| ListedApp { appid: a.id, name: a.name })
+                .collect();
+            serde_json::to_string(&AppListResponse { applist: AppList { apps } })
+        },
+        VANITY_PATH => {
+            let response = match steam.resolve_vanity_user(param("vanityurl")).await {
+                Ok(id) => VanityResponse { success: 1, steamid: Some(id.to_string()) },
+                Err(_) => VanityResponse { success: 42, steamid: None },
+            };
+            serde_json::to_string(&Wrapped { response })
+        },
+        OWNED_GAMES_PATH => {
+            // private and unknown libraries come back without any games
+            let response = match param("steamid").parse::<u64>() {
+                Ok(id) => {
+                    let games = steam.owned_games(&SteamID::from(id)).await.unwrap_or_default();
+                    OwnedGamesResponse { game_count: games.len() as u32, games }
+                },
+                Err(_) => OwnedGamesResponse::default(),
+            };
+            serde_json::to_string(&Wrapped { response })
+        },
+        SUMMARIES_PATH => {
+            let ids: Vec<SteamID> = param("steamids").split(',')
+                .filter_map(|id| id.parse::<u64>().ok())
+                .map(SteamID::from)
+                .collect();
+            let players = steam.player_summaries(&ids).await.unwrap_or_default().into_iter()
+                .map(|p| Player {
+                    steamid: p.id.to_string(),
+                    personaname: p.name,
+                    profileurl: p.url,
+                    communityvisibilitystate: (&p.visibility).into()
+                })
+                .collect();
+            serde_json::to_string(&Wrapped { response: PlayersResponse { players } })
+        },
+        APP_DETAILS_PATH => {
+            let id = param("appids");
+            let data = match id.parse::<u32>() {
+                Ok(i) => steam.app_details(i).await.ok(),
+                Err(_) => None,
+            };
+            let mut details = HashMap::new();
+            details.insert(id.to_string(), AppDetailsResponse { success: data.is_some(), data });
+            serde_json::to_string(&details)
+        },
+        _ => return None
+    };
+    body.ok()
+}
+
+/// Splits a query string into decoded names and values.
+fn query_params(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (decode(name), decode(value)))
+        .collect()
+}
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match bytes.get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(b) => {
+                    decoded.push(b);
+                    i += 2;
+                },
+                None => decoded.push(b'%'),
+            },
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use rsteam::SteamID;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serenity::async_trait;
+
+use super::{App, AppDetails, OwnedGame, PlayerSummary, SteamApi, Visibility};
+
+pub const API_URL: &str = "https://api.steampowered.com";
+pub const STORE_URL: &str = "https://store.steampowered.com";
+
+pub(super) const APP_LIST_PATH: &str = "/ISteamApps/GetAppList/v2/";
+pub(super) const VANITY_PATH: &str = "/ISteamUser/ResolveVanityURL/v0001/";
+pub(super) const OWNED_GAMES_PATH: &str = "/IPlayerService/GetOwnedGames/v0001/";
+pub(super) const SUMMARIES_PATH: &str = "/ISteamUser/GetPlayerSummaries/v0002/";
+pub(super) const APP_DETAILS_PATH: &str = "/api/appdetails";
+
+// the api's own layouts, shared with the stub server
+#[derive(Deserialize,Serialize)]
+pub(super) struct Wrapped<T> {
+    pub response: T,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct AppListResponse {
+    pub applist: AppList,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct AppList {
+    pub apps: Vec<ListedApp>,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct ListedApp {
+    pub appid: u32,
+    pub name: String,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct VanityResponse {
+    pub success: u8,
+    #[serde(default)]
+    pub steamid: Option<String>,
+}
+#[derive(Default,Deserialize,Serialize)]
+pub(super) struct OwnedGamesResponse {
+    #[serde(default)]
+    pub game_count: u32,
+    #[serde(default)]
+    pub games: Vec<OwnedGame>,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct PlayersResponse {
+    pub players: Vec<Player>,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct Player {
+    pub steamid: String,
+    pub personaname: String,
+    pub profileurl: String,
+    /// 3 for public profiles, anything else can't be read.
+    pub communityvisibilitystate: u8,
+}
+#[derive(Deserialize,Serialize)]
+pub(super) struct AppDetailsResponse {
+    pub success: bool,
+    #[serde(default)]
+    pub data: Option<AppDetails>,
+}
+
+impl From<&Visibility> for u8 {
+    fn from(visibility: &Visibility) -> Self {
+        match visibility {
+            Visibility::Private => 1,
+            Visibility::FriendsOnly => 2,
+            Visibility::Public => 3,
+        }
+    }
+}
+impl From<u8> for Visibility {
+    fn from(state: u8) -> Self {
+        match state {
+            3 => Visibility::Public,
+            2 => Visibility::FriendsOnly,
+            _ => Visibility::Private,
+        }
+    }
+}
+
+/// The real steam web api and store, or anything serving the same
+/// endpoints such as the stub server.
+pub struct WebApi {
+    key: String,
+    api_url: String,
+    store_url: String,
+    http: reqwest::Client,
+}
+impl WebApi {
+    pub fn with_api_key(key: &str) -> Self {
+        WebApi {
+            key: key.to_string(),
+            api_url: API_URL.to_string(),
+            store_url: STORE_URL.to_string(),
+            http: reqwest::Client::new()
+        }
+    }
+    /// Sends both web api and store requests to another server.
+    pub fn with_base_url(key: &str, url: &str) -> Self {
+        let url = url.trim_end_matches('/');
+        WebApi {
+            api_url: url.to_string(),
+            store_url: url.to_string(),
+            ..WebApi::with_api_key(key)
+        }
+    }
+    async fn get<T: DeserializeOwned>(&self, url: String, query: &[(&str, String)]) -> Result<T, ()> {
+        let response = match self.http.get(url).query(query).send().await {
+            Ok(r) => r,
+            Err(_) => return Err(())
+        };
+        match response.error_for_status() {
+            Ok(r) => r.json().await.map_err(|_| ()),
+            Err(_) => Err(())
+        }
+    }
+    async fn api<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, ()> {
+        let mut query = query.to_vec();
+        query.push(("key", self.key.clone()));
+        self.get(format!{"{}{}", self.api_url, path}, &query).await
+    }
+}
+
+#[async_trait]
+impl SteamApi for WebApi {
+    async fn app_list(&self) -> Result<Vec<App>, ()> {
+        let list: AppListResponse = self.api(APP_LIST_PATH, &[]).await?;
+        Ok(list.applist.apps.into_iter()
+            .map(|a| App { id: a.appid, name: a.name })
+            .collect())
+    }
+    async fn resolve_vanity_user(&self, name: &str) -> Result<SteamID, ()> {
+        let vanity: Wrapped<VanityResponse> = self.api(VANITY_PATH, &[("vanityurl", name.to_string())]).await?;
+        match (vanity.response.success, vanity.response.steamid) {
+            (1, Some(id)) => id.parse::<u64>().map(SteamID::from).map_err(|_| ()),
+            _ => Err(())
+        }
+    }
+    async fn owned_games(&self, user: &SteamID) -> Result<Vec<OwnedGame>, ()> {
+        let owned: Wrapped<OwnedGamesResponse> = self.api(OWNED_GAMES_PATH, &[
+            ("steamid", user.to_string()),
+            ("include_appinfo", "1".to_string()),
+            ("include_played_free_games", "1".to_string()),
+            ("skip_unvetted_apps", "1".to_string()),
+        ]).await?;
+        Ok(owned.response.games)
+    }
+    async fn player_summaries(&self, users: &[SteamID]) -> Result<Vec<PlayerSummary>, ()> {
+        let ids: Vec<String> = users.iter().map(|u| u.to_string()).collect();
+        let players: Wrapped<PlayersResponse> = self.api(SUMMARIES_PATH, &[("steamids", ids.join(","))]).await?;
+        Ok(players.response.players.into_iter()
+            .filter_map(|p| Some(PlayerSummary {
+                id: p.steamid.parse::<u64>().ok()?.into(),
+                name: p.personaname,
+                url: p.profileurl,
+                visibility: p.communityvisibilitystate.into()
+            }))
+            .collect())
+    }
+    async fn app_details(&self, id: u32) -> Result<AppDetails, ()> {
+        let url = format!{"{}{}", self.store_url, APP_DETAILS_PATH};
+        let mut details: HashMap<String, AppDetailsResponse> = self.get(url, &[("appids", id.to_string())]).await?;
+        match details.remove(&id.to_string()) {
+            Some(AppDetailsResponse { success: true, data: Some(d) }) => Ok(d),
+            _ => Err(()) // TODO: ERROR NOT FOUND
+        }
+    }
+}
@@ -1,20 +1,61 @@
 use serenity::async_trait;
+use tracing::info;
 use serenity::prelude::*;
 use serenity::{
     model::{
+        channel::Reaction,
         event::ResumedEvent,
         gateway::Ready,
+        guild::{Guild, GuildUnavailable, Member},
+        id::GuildId,
+        interactions::Interaction,
+        user::User,
     }
 };
 
+use crate::commands::{cleanup, components, gamenights, lifecycle, listing, slash, votes};
+
 pub struct Handler;
 
 #[async_trait]
 impl EventHandler for Handler {
-    async fn ready(&self, _: Context, ready: Ready) {
-        println!("{} is ready and connected!", ready.user.name);
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        listing::handle_reaction(&ctx, &reaction).await;
+        votes::handle_reaction(&ctx, &reaction, true).await;
+        lifecycle::handle_reaction(&ctx, &reaction).await;
+        gamenights::handle_reaction(&ctx, &reaction, true).await;
+    }
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        votes::handle_reaction(&ctx, &reaction, false).await;
+        gamenights::handle_reaction(&ctx, &reaction, false).await;
+    }
+    async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: bool) {
+        cleanup::guild_joined(&ctx, guild.id).await;
+    }
+    async fn guild_delete(&self, ctx: Context, incomplete: GuildUnavailable, _full: Option<Guild>) {
+        // unavailable guilds are outages, not removals
+        if !incomplete.unavailable {
+            cleanup::guild_left(&ctx, incomplete.id).await;
+        }
+    }
+    async fn guild_member_addition(&self, ctx: Context, gid: GuildId, member: Member) {
+        cleanup::member_joined(&ctx, gid, member.user.id).await;
+    }
+    async fn guild_member_removal(&self, ctx: Context, gid: GuildId, user: User, _member: Option<Member>) {
+        cleanup::member_left(&ctx, gid, user.id).await;
+    }
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::MessageComponent(component) => components::handle(&ctx, &component).await,
+            other => slash::handle(&ctx, other).await,
+        }
+    }
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        info!("{} is ready and connected!", ready.user.name);
+        slash::register(&ctx).await;
+        gamenights::start_scheduler(&ctx);
     }
     async fn resume(&self, _: Context, resume: ResumedEvent) {
-        println!("Resuming events: {:?}", resume.trace);
+        info!("Resuming events: {:?}", resume.trace);
     }
 }
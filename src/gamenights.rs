@@ -0,0 +1,312 @@
+use std::collections::BTreeSet;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+use serenity::model::id::{ChannelId, MessageId, UserId};
+
+/// How often a game night comes around again.
+#[derive(Clone,Copy,Debug,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    Weekly,
+}
+
+/// What the bot does once a game night starts.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartAction {
+    /// A poll between the common games of everyone who is coming.
+    #[default]
+    Poll,
+    /// A random one of their common games.
+    Pick,
+}
+
+/// When a game night is and what happens when it starts, as written when
+/// creating it.
+#[derive(Debug,PartialEq)]
+pub struct Schedule {
+    pub start: DateTime<Utc>,
+    /// Zone the time was written in, that weekly nights keep to.
+    pub zone: Tz,
+    pub recurrence: Option<Recurrence>,
+    pub action: StartAction,
+}
+impl Schedule {
+    /// Parses `<day> <HH:MM> [recurring weekly] [poll|pick]`, where the day
+    /// is a weekday, `today`, `tomorrow` or a `YYYY-MM-DD` date, read in
+    /// `zone`. Weekdays mean the next one whose time is still to come.
+    pub fn parse(words: &[String], now: DateTime<Utc>, zone: Tz) -> Result<Schedule, String> {
+        let (day, time) = match words {
+            [day, time, ..] => (day.to_ascii_lowercase(), time),
+            _ => return Err("Usage: gamenight create <day> <HH:MM> [recurring weekly] [poll|pick]".to_string())
+        };
+        let time = NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!{"Invalid time '{}', use 24 hour HH:MM.", time})?;
+        let local = now.with_timezone(&zone).naive_local();
+        let today = local.date();
+        let date = match day.as_str() {
+            "today" => today,
+            "tomorrow" => today + Duration::days(1),
+            day => match (day.parse::<Weekday>(), NaiveDate::parse_from_str(day, "%Y-%m-%d")) {
+                (Ok(weekday), _) => {
+                    let ahead = (7 + weekday.num_days_from_monday() as i64
+                        - today.weekday().num_days_from_monday() as i64) % 7;
+                    let ahead = if ahead == 0 && time <= local.time() { 7 } else { ahead };
+                    today + Duration::days(ahead)
+                },
+                (_, Ok(date)) => date,
+                _ => return Err(format!{"Invalid day '{}'. Try a weekday, 'today', 'tomorrow' or YYYY-MM-DD.", day})
+            }
+        };
+        let start = match zone.from_local_datetime(&date.and_time(time)).earliest() {
+            Some(s) => s.with_timezone(&Utc),
+            None => return Err("That time is skipped by a clock change, pick another.".to_string())
+        };
+        if start <= now {
+            return Err("That time has already passed.".to_string());
+        }
+        let mut schedule = Schedule { start, zone, recurrence: None, action: StartAction::default() };
+        for word in words[2..].iter().map(|w| w.to_ascii_lowercase()) {
+            match word.as_str() {
+                "recurring" => {},
+                "weekly" => schedule.recurrence = Some(Recurrence::Weekly),
+                "poll" => schedule.action = StartAction::Poll,
+                "pick" => schedule.action = StartAction::Pick,
+                _ => return Err(format!{"Unknown option '{}'. Try 'recurring weekly', 'poll' or 'pick'.", word})
+            };
+        }
+        Ok(schedule)
+    }
+}
+
+/// A scheduled evening of games that members RSVP to.
+#[derive(Clone,Debug,Deserialize,Serialize)]
+pub struct GameNight {
+    pub id: u32,
+    pub creator: UserId,
+    pub channel: ChannelId,
+    /// The announcement members react to, `None` until it is posted.
+    #[serde(default)]
+    pub message: Option<MessageId>,
+    pub start: DateTime<Utc>,
+    #[serde(default = "utc")]
+    pub zone: Tz,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub action: StartAction,
+    #[serde(default)]
+    pub rsvps: BTreeSet<UserId>,
+    #[serde(default)]
+    pub reminded: bool,
+    /// Whether an announcement is being posted, so it is posted once.
+    #[serde(skip)]
+    announcing: bool,
+}
+// game nights saved before time zones were all in UTC
+fn utc() -> Tz {
+    Tz::UTC
+}
+
+impl GameNight {
+    /// Moves a recurring game night on to its next time after `now`, with
+    /// a fresh announcement and RSVPs. Returns false for one-off nights.
+    /// The time is kept in the night's zone, so it holds across clock
+    /// changes.
+    fn advance(&mut self, now: DateTime<Utc>) -> bool {
+        let step = match self.recurrence {
+            Some(Recurrence::Weekly) => Duration::weeks(1),
+            None => return false
+        };
+        let mut local = self.start.with_timezone(&self.zone).naive_local();
+        while self.start <= now {
+            local += step;
+            self.start = match self.zone.from_local_datetime(&local).earliest() {
+                Some(s) => s.with_timezone(&Utc),
+                None => self.start + step
+            };
+        }
+        self.message = None;
+        self.rsvps.clear();
+        self.reminded = false;
+        true
+    }
+}
+
+/// All game nights for a single guild.
+#[derive(Default,Deserialize,Serialize)]
+pub struct GameNightBook {
+    next_id: u32,
+    nights: Vec<GameNight>,
+}
+impl GameNightBook {
+    /// Schedules a game night under the next free id and returns it, as
+    /// being announced by the caller, who reports back with `announced`.
+    pub fn add(&mut self, creator: UserId, channel: ChannelId, schedule: Schedule) -> &mut GameNight {
+        self.next_id += 1;
+        self.nights.push(GameNight {
+            id: self.next_id,
+            creator,
+            channel,
+            message: None,
+            start: schedule.start,
+            zone: schedule.zone,
+            recurrence: schedule.recurrence,
+            action: schedule.action,
+            rsvps: BTreeSet::new(),
+            reminded: false,
+            announcing: true
+        });
+        self.nights.last_mut().expect("pushed above")
+    }
+    pub fn get(&self, id: u32) -> Option<&GameNight> {
+        self.nights.iter().find(|n| n.id == id)
+    }
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut GameNight> {
+        self.nights.iter_mut().find(|n| n.id == id)
+    }
+    pub fn by_message(&mut self, message: MessageId) -> Option<&mut GameNight> {
+        self.nights.iter_mut().find(|n| n.message == Some(message))
+    }
+    pub fn remove(&mut self, id: u32) -> Option<GameNight> {
+        let index = self.nights.iter().position(|n| n.id == id)?;
+        Some(self.nights.remove(index))
+    }
+    /// Game nights in the order they start.
+    pub fn upcoming(&self) -> Vec<&GameNight> {
+        let mut nights: Vec<&GameNight> = self.nights.iter().collect();
+        nights.sort_by_key(|n| n.start);
+        nights
+    }
+    /// Game nights still to be announced that no one is announcing yet,
+    /// marking them as being announced.
+    pub fn claim_unannounced(&mut self) -> Vec<GameNight> {
+        self.nights.iter_mut()
+            .filter(|n| n.message.is_none() && !n.announcing)
+            .map(|n| {
+                n.announcing = true;
+                n.clone()
+            })
+            .collect()
+    }
+    /// Settles an announcement, with the message posted or `None` when
+    /// posting failed and it should be tried again.
+    pub fn announced(&mut self, id: u32, message: Option<MessageId>) {
+        if let Some(night) = self.get_mut(id) {
+            night.announcing = false;
+            night.message = message;
+        }
+    }
+    /// Game nights starting within `lead` of `now` that haven't been
+    /// reminded of yet, marking them reminded.
+    pub fn due_reminders(&mut self, now: DateTime<Utc>, lead: Duration) -> Vec<GameNight> {
+        self.nights.iter_mut()
+            .filter(|n| !n.reminded && n.start > now && n.start - now <= lead)
+            .map(|n| {
+                n.reminded = true;
+                n.clone()
+            })
+            .collect()
+    }
+    /// Game nights that started by `now`, as they were. Recurring ones stay
+    /// on for their next time, the others are removed.
+    pub fn take_started(&mut self, now: DateTime<Utc>) -> Vec<GameNight> {
+        let started: Vec<GameNight> = self.nights.iter().filter(|n| n.start <= now).cloned().collect();
+        self.nights.retain_mut(|n| n.start > now || n.advance(now));
+        started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe;
+
+    fn words(text: &str) -> Vec<String> {
+        text.split_whitespace().map(|w| w.to_string()).collect()
+    }
+
+    // a wednesday
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2024, 5, 15).and_hms(18, 0, 0)
+    }
+
+    #[test]
+    fn parses_days_and_times() {
+        let friday = Schedule::parse(&words("Friday 20:00"), now(), Tz::UTC).unwrap();
+        assert_eq!(friday, Schedule { start: Utc.ymd(2024, 5, 17).and_hms(20, 0, 0), zone: Tz::UTC, recurrence: None, action: StartAction::Poll });
+        assert_eq!(Schedule::parse(&words("wed 19:30"), now(), Tz::UTC).unwrap().start, Utc.ymd(2024, 5, 15).and_hms(19, 30, 0));
+        assert_eq!(Schedule::parse(&words("wed 17:00"), now(), Tz::UTC).unwrap().start, Utc.ymd(2024, 5, 22).and_hms(17, 0, 0));
+        assert_eq!(Schedule::parse(&words("tomorrow 08:00"), now(), Tz::UTC).unwrap().start, Utc.ymd(2024, 5, 16).and_hms(8, 0, 0));
+        assert_eq!(Schedule::parse(&words("2024-06-01 21:15"), now(), Tz::UTC).unwrap().start, Utc.ymd(2024, 6, 1).and_hms(21, 15, 0));
+        let weekly = Schedule::parse(&words("sat 20:00 recurring weekly pick"), now(), Tz::UTC).unwrap();
+        assert_eq!((weekly.recurrence, weekly.action), (Some(Recurrence::Weekly), StartAction::Pick));
+    }
+
+    #[test]
+    fn reads_times_in_the_given_zone() {
+        let schedule = Schedule::parse(&words("today 21:00"), now(), Europe::Berlin).unwrap();
+        assert_eq!(schedule.start, Utc.ymd(2024, 5, 15).and_hms(19, 0, 0));
+    }
+
+    #[test]
+    fn keeps_weekly_nights_at_local_time_across_clock_changes() {
+        // the clocks in Berlin go back an hour on 2024-10-27
+        let mut book = GameNightBook::default();
+        let october = Utc.ymd(2024, 10, 20).and_hms(12, 0, 0);
+        let schedule = Schedule::parse(&words("today 20:00 recurring weekly"), october, Europe::Berlin).unwrap();
+        assert_eq!(schedule.start, Utc.ymd(2024, 10, 20).and_hms(18, 0, 0));
+        book.add(UserId(1), ChannelId(1), schedule);
+        book.take_started(Utc.ymd(2024, 10, 20).and_hms(18, 0, 0));
+        assert_eq!(book.get(1).unwrap().start, Utc.ymd(2024, 10, 27).and_hms(19, 0, 0));
+    }
+
+    #[test]
+    fn rejects_bad_schedules() {
+        assert!(Schedule::parse(&words("Friday"), now(), Tz::UTC).is_err());
+        assert!(Schedule::parse(&words("Friday 8pm"), now(), Tz::UTC).is_err());
+        assert!(Schedule::parse(&words("someday 20:00"), now(), Tz::UTC).is_err());
+        assert!(Schedule::parse(&words("today 17:00"), now(), Tz::UTC).is_err());
+        assert!(Schedule::parse(&words("Friday 20:00 daily"), now(), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn reminds_once_and_moves_weekly_nights_on() {
+        let mut book = GameNightBook::default();
+        let once = Schedule::parse(&words("today 20:00"), now(), Tz::UTC).unwrap();
+        let weekly = Schedule::parse(&words("today 20:00 recurring weekly"), now(), Tz::UTC).unwrap();
+        book.add(UserId(1), ChannelId(1), once);
+        book.add(UserId(1), ChannelId(1), weekly).rsvps.insert(UserId(2));
+        book.announced(1, Some(MessageId(10)));
+        book.announced(2, Some(MessageId(11)));
+
+        assert!(book.due_reminders(now(), Duration::minutes(30)).is_empty());
+        let soon = now() + Duration::minutes(100);
+        assert_eq!(book.due_reminders(soon, Duration::minutes(30)).len(), 2);
+        assert!(book.due_reminders(soon, Duration::minutes(30)).is_empty());
+
+        let started = book.take_started(now() + Duration::hours(2));
+        assert_eq!(started.len(), 2);
+        assert!(started[1].rsvps.contains(&UserId(2)));
+        assert!(book.get(1).is_none());
+        let next = book.get(2).unwrap();
+        assert_eq!(next.start, Utc.ymd(2024, 5, 22).and_hms(20, 0, 0));
+        assert!(next.rsvps.is_empty() && !next.reminded);
+        assert_eq!(book.claim_unannounced().len(), 1);
+    }
+
+    #[test]
+    fn announces_each_night_once() {
+        let mut book = GameNightBook::default();
+        book.add(UserId(1), ChannelId(1), Schedule::parse(&words("Friday 20:00"), now(), Tz::UTC).unwrap());
+        // still being announced by whoever created it
+        assert!(book.claim_unannounced().is_empty());
+        book.announced(1, None);
+        assert_eq!(book.claim_unannounced().len(), 1);
+        assert!(book.claim_unannounced().is_empty());
+        book.announced(1, Some(MessageId(10)));
+        assert!(book.by_message(MessageId(10)).is_some());
+        assert!(book.claim_unannounced().is_empty());
+    }
+}
@@ -0,0 +1,18 @@
+//! Everything the bot knows without talking to discord: suggestion books,
+//! player profiles and common games, the random picker, polls, game
+//! nights, when members are available and how often they may use commands.
+//! The bot's commands adapt these to messages and reactions.
+
+// lookups that fail return `Err(())` and callers say what wasn't found
+#![allow(clippy::result_unit_err)]
+
+pub mod endpoints;
+pub mod suggestions;
+pub mod players;
+pub mod picker;
+pub mod polls;
+pub mod gamenights;
+pub mod availability;
+pub mod ratelimits;
+pub mod tags;
+pub mod titles;
@@ -1,15 +1,15 @@
 use std::{
-    collections::HashSet,
-    sync::Arc
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration
 };
-
-//use clap::{App as ClapApp, Arg, crate_version, SubCommand, ArgMatches};
+use tracing::error;
 
 use serenity::{
     prelude::*,
     client::{
         Client,
-        bridge::gateway::ShardManager
+        bridge::gateway::{GatewayIntents, ShardManager}
     },
     http::Http,
     framework::standard::StandardFramework
@@ -18,17 +18,28 @@ use serenity::{
 mod config;
 mod events;
 mod commands;
-mod endpoints;
 
 use crate::commands::{
     GENERAL_GROUP,
     SUGGESTIONS_GROUP, GameSuggestions,
     PLAYERS_GROUP, PlayerContainer,
-    
+    SETTINGS_GROUP, GuildSettingsContainer,
+    POLLS_GROUP, StrawPollKey,
+    VOTES_GROUP, VoteMessages,
+    TAGS_GROUP,
+    TITLES_GROUP,
+    LIFECYCLE_GROUP, PickMessages, PickVetoes,
+    GAMENIGHTS_GROUP, GameNightContainer,
 };
+use crate::commands::permissions::dispatch_error;
+use crate::commands::listing::ListingSessions;
+use crate::commands::cleanup::{self, DepartedGuilds};
+use crate::commands::settings::{guild_prefix, bot_channel_only, RateLimiterContainer};
 use crate::events::Handler;
-use crate::endpoints::steam;
-use crate::config::Config;
+use crate::config::{Config, SteamBackend};
+
+use game_randomizer::endpoints::{steam, GameSource, GameSources};
+use game_randomizer::ratelimits::RateLimiter;
 
 pub struct ShardManagerContainer;
 
@@ -38,22 +49,25 @@ impl TypeMapKey for ShardManagerContainer {
 
 #[tokio::main]
 async fn main() {
-    let config = match Config::from_file("./config.toml") {
+    let config = match Config::from_args() {
         Ok(c) => c,
-        Err(_) => panic!{"Unable to read config"}
-    };
-    let suggestions = match config.load_suggestions() {
-        Some(s) => s,
-        None => GameSuggestions::new()
-    };
-    let players = match config.load_players() {
-        Some(s) => s,
-        None => PlayerContainer::new()
+        Err(why) => {
+            eprintln!{"Unable to read config: {}", why};
+            std::process::exit(1);
+        }
     };
+    tracing_subscriber::fmt()
+        .with_max_level(config.log_level)
+        .init();
+    let suggestions = config.load_suggestions().unwrap_or_default();
+    let players = config.load_players().unwrap_or_default();
+    let settings = config.load_settings().unwrap_or_default();
+    let departed = config.load_departed().unwrap_or_default();
+    let gamenights = config.load_gamenights().unwrap_or_default();
     
     // access bot owners to restrict commands
     let http = Http::new_with_token(&config.discord);
-    let (owners, _bot_id) = match http.get_current_application_info().await {
+    let (owners, bot_id) = match http.get_current_application_info().await {
         Ok(info) => {
             let mut owners = HashSet::new();
             owners.insert(info.owner.id);
@@ -63,28 +77,80 @@ async fn main() {
     };
     // setup command framework
     let framework = StandardFramework::new()
-        .configure(|c| c.owners(owners).prefix("~"))
+        .configure(|c| c
+            .owners(owners)
+            .prefix("")
+            .dynamic_prefix(|ctx, msg| Box::pin(guild_prefix(ctx, msg))))
+        .before(bot_channel_only)
+        .on_dispatch_error(dispatch_error)
         .group(&GENERAL_GROUP)
         .group(&SUGGESTIONS_GROUP)
-        .group(&PLAYERS_GROUP);
+        .group(&PLAYERS_GROUP)
+        .group(&SETTINGS_GROUP)
+        .group(&POLLS_GROUP)
+        .group(&VOTES_GROUP)
+        .group(&TAGS_GROUP)
+        .group(&TITLES_GROUP)
+        .group(&LIFECYCLE_GROUP)
+        .group(&GAMENIGHTS_GROUP);
     // Login with a bot token from the environment
+    // member removals need the privileged guild members intent, slash
+    // commands the application id
     let mut client = Client::builder(&config.discord)
+        .application_id(*bot_id.as_u64())
+        .intents(GatewayIntents::non_privileged() | GatewayIntents::GUILD_MEMBERS)
         .event_handler(Handler)
         .framework(framework)
         .await
         .expect("Error creating client");
     // add shared data
     // TODO use config.storage for saving
-    let mut steamclient = steam::Client::with_api_key(&config.steam);
+    let mut steamclient = match &config.steam_backend {
+        SteamBackend::Steam => steam::Client::with_api_key(&config.steam),
+        SteamBackend::Url(url) => steam::Client::new(steam::WebApi::with_base_url(&config.steam, url)),
+        SteamBackend::Stub(fixture) => {
+            let fake = steam::FakeSteam::from_fixture(fixture).expect("Could not load steam fixture");
+            let addr = steam::stub::start(fake).await.expect("Could not start steam stub");
+            steam::Client::new(steam::WebApi::with_base_url(&config.steam, &format!{"http://{}", addr}))
+        }
+    };
     steamclient.fill_app_list().await.expect("Could not fill steam app list");
+    let steamclient = Arc::new(Mutex::new(steamclient));
+    let mut sources = HashMap::new();
+    let clients: [Arc<Mutex<dyn GameSource>>; 1] = [
+        steamclient.clone(),
+    ];
+    for client in clients {
+        let source = client.lock().await.source();
+        sources.insert(source, client);
+    }
     {
         let mut data = client.data.write().await;
         data.insert::<Config>(config);
         data.insert::<GameSuggestions>(suggestions);
         data.insert::<PlayerContainer>(players);
+        data.insert::<GuildSettingsContainer>(settings);
+        data.insert::<DepartedGuilds>(departed);
+        data.insert::<GameNightContainer>(gamenights);
+        data.insert::<StrawPollKey>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<ListingSessions>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<VoteMessages>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<PickMessages>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<PickVetoes>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<RateLimiterContainer>(RateLimiter::default());
         data.insert::<ShardManagerContainer>(client.shard_manager.clone());
-        data.insert::<steam::Client>(Arc::new(Mutex::new(steamclient)));
+        data.insert::<steam::Client>(steamclient);
+        data.insert::<GameSources>(sources);
     }
+    // purge guilds the bot was removed from once their grace period ends
+    let data = client.data.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            cleanup::purge_departed(&data).await;
+        }
+    });
     // spawn shard manager threads
     let shard_manager = client.shard_manager.clone();
     tokio::spawn(async move {
@@ -93,7 +159,7 @@ async fn main() {
     });
     // start listening for events by starting a single shard
     if let Err(why) = client.start().await {
-        println!("An error occurred while running the client: {:?}", why);
+        error!("An error occurred while running the client: {:?}", why);
     }
 }
 
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::fmt;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::suggestions::{SuggestedGame, Suggestion, SuggestionBook};
+
+/// Largest weight a guild can set.
+pub const MAX_WEIGHT: u32 = 1000;
+
+/// Relative weights used by the random picker for store and plain text
+/// suggestions, plus the extra weight given for each point of vote score.
+#[derive(Clone,Debug,Deserialize,Serialize)]
+#[serde(default)]
+pub struct PickerWeights {
+    #[serde(alias = "steam")]
+    pub store: u32,
+    pub plain: u32,
+    pub votes: u32,
+}
+impl PickerWeights {
+    /// Checks the weights are at most `MAX_WEIGHT`.
+    pub fn new(store: u32, plain: u32, votes: u32) -> Result<Self, String> {
+        if store.max(plain).max(votes) > MAX_WEIGHT {
+            return Err(format!{"Weights can be at most {}.", MAX_WEIGHT});
+        }
+        Ok(PickerWeights { store, plain, votes })
+    }
+}
+impl Default for PickerWeights {
+    fn default() -> Self {
+        PickerWeights {
+            store: 1,
+            plain: 1,
+            votes: 1
+        }
+    }
+}
+impl fmt::Display for PickerWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "store {} | plain {} | per vote {}", self.store, self.plain, self.votes)
+    }
+}
+
+/// A suggestion's type weight, raised by the votes weight for each point of
+/// positive score. Types weighted at zero are never picked.
+pub fn pick_weight(weights: &PickerWeights, suggestion: &Suggestion) -> u32 {
+    let base = match suggestion.game {
+        SuggestedGame::Store(..) => weights.store,
+        SuggestedGame::PlainText(..) => weights.plain,
+    };
+    if base == 0 {
+        return 0;
+    }
+    let score = u32::try_from(suggestion.score()).unwrap_or(0);
+    base.saturating_add(weights.votes.saturating_mul(score))
+}
+
+/// Suggestions left out of picks for a while, such as for the rest of the
+/// night after someone vetoed them.
+#[derive(Default)]
+pub struct Vetoes(HashMap<u32, DateTime<Utc>>);
+impl Vetoes {
+    pub fn veto(&mut self, id: u32, until: DateTime<Utc>) {
+        self.0.insert(id, until);
+    }
+    /// Ids still vetoed at `now`, forgetting those that ran out.
+    pub fn active(&mut self, now: DateTime<Utc>) -> Vec<u32> {
+        self.0.retain(|_, until| *until > now);
+        self.0.keys().copied().collect()
+    }
+}
+
+/// Picks one of the active suggestions carrying every tag, at random by
+/// their picker weight. Suggestions in `skip` are never picked.
+pub fn pick<'a, R: Rng + ?Sized>(
+    book: &'a SuggestionBook,
+    weights: &PickerWeights,
+    tags: &[String],
+    skip: &[u32],
+    rng: &mut R
+) -> Result<&'a Suggestion, String> {
+    let candidates: Vec<&Suggestion> = book.iter()
+        .filter(|s| s.state.is_active() && s.has_tags(tags) && !skip.contains(&s.id))
+        .collect();
+    if candidates.is_empty() {
+        return Err("There are no suggestions to pick from.".to_string());
+    }
+    // summed as u64 so that saturated weights can't overflow the total
+    match WeightedIndex::new(candidates.iter().map(|s| pick_weight(weights, s) as u64)) {
+        Ok(dist) => Ok(candidates[dist.sample(rng)]),
+        Err(_) => Err("There are no suggestions with a picker weight above zero.".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use serenity::model::id::UserId;
+    use crate::endpoints::{Source, StoreGame};
+    use crate::suggestions::{SuggestionState, TextSuggestion, Vote};
+
+    fn plain(title: &str) -> SuggestedGame {
+        SuggestedGame::PlainText(TextSuggestion { title: title.to_string(), ..TextSuggestion::default() })
+    }
+    fn store(id: &str, name: &str) -> SuggestedGame {
+        SuggestedGame::Store(StoreGame { source: Source::Steam, id: id.to_string(), name: name.to_string() })
+    }
+
+    #[test]
+    fn weighs_types_and_votes() {
+        let mut book = SuggestionBook::default();
+        let weights = PickerWeights { store: 3, plain: 1, votes: 2 };
+        let id = book.add(UserId(1), plain("Valheim")).unwrap().id;
+        assert_eq!(pick_weight(&weights, book.get(id).unwrap()), 1);
+        let voted = book.get_mut(id).unwrap();
+        voted.vote(UserId(2), Some(Vote::Up));
+        voted.vote(UserId(3), Some(Vote::Up));
+        assert_eq!(pick_weight(&weights, voted), 5);
+        voted.vote(UserId(4), Some(Vote::Down));
+        voted.vote(UserId(5), Some(Vote::Down));
+        voted.vote(UserId(6), Some(Vote::Down));
+        assert_eq!(pick_weight(&weights, voted), 1);
+        let game = book.add(UserId(1), store("620", "Portal 2")).unwrap();
+        assert_eq!(pick_weight(&weights, game), 3);
+        assert_eq!(pick_weight(&PickerWeights { store: 0, ..weights }, game), 0);
+    }
+
+    #[test]
+    fn saturates_large_weights() {
+        let mut book = SuggestionBook::default();
+        let voted = book.add(UserId(1), plain("Valheim")).unwrap();
+        voted.vote(UserId(2), Some(Vote::Up));
+        voted.vote(UserId(3), Some(Vote::Up));
+        let weights = PickerWeights { store: u32::MAX, plain: u32::MAX, votes: u32::MAX };
+        assert_eq!(pick_weight(&weights, voted), u32::MAX);
+        book.add(UserId(1), plain("Portal 2")).unwrap();
+        assert!(pick(&book, &weights, &[], &[], &mut StdRng::seed_from_u64(1)).is_ok());
+        assert!(PickerWeights::new(MAX_WEIGHT, 1, 1).is_ok());
+        assert!(PickerWeights::new(1, 1, MAX_WEIGHT + 1).is_err());
+    }
+
+    #[test]
+    fn picks_only_active_tagged_and_weighted() {
+        let mut book = SuggestionBook::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let weights = PickerWeights::default();
+        assert!(pick(&book, &weights, &[], &[], &mut rng).is_err());
+
+        book.add(UserId(1), plain("Deep Rock Galactic")).unwrap().tags.insert("co-op".to_string());
+        book.add(UserId(1), plain("Portal 2")).unwrap()
+            .transition(SuggestionState::Played, UserId(1)).unwrap();
+        book.add(UserId(2), store("892970", "Valheim")).unwrap();
+        let tags = vec!["co-op".to_string()];
+        for _ in 0..20 {
+            assert_eq!(pick(&book, &weights, &tags, &[], &mut rng).unwrap().title(), "Deep Rock Galactic");
+            assert_ne!(pick(&book, &weights, &[], &[], &mut rng).unwrap().title(), "Portal 2");
+        }
+        let store_only = PickerWeights { plain: 0, ..PickerWeights::default() };
+        for _ in 0..20 {
+            assert_eq!(pick(&book, &store_only, &[], &[], &mut rng).unwrap().title(), "Valheim");
+        }
+        let nothing = PickerWeights { store: 0, plain: 0, votes: 1 };
+        assert!(pick(&book, &nothing, &[], &[], &mut rng).is_err());
+    }
+
+    #[test]
+    fn skips_vetoed_until_they_run_out() {
+        let mut book = SuggestionBook::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let weights = PickerWeights::default();
+        let vetoed = book.add(UserId(1), plain("Valheim")).unwrap().id;
+        book.add(UserId(1), plain("Portal 2")).unwrap();
+        let now = Utc::now();
+        let mut vetoes = Vetoes::default();
+        vetoes.veto(vetoed, now + chrono::Duration::hours(12));
+        let skip = vetoes.active(now);
+        assert_eq!(skip, vec![vetoed]);
+        for _ in 0..20 {
+            assert_eq!(pick(&book, &weights, &[], &skip, &mut rng).unwrap().title(), "Portal 2");
+        }
+        assert!(vetoes.active(now + chrono::Duration::hours(13)).is_empty());
+        let all: Vec<u32> = book.iter().map(|s| s.id).collect();
+        assert!(pick(&book, &weights, &[], &all, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::*;
+use rsteam::SteamID;
+use chrono_tz::Tz;
+
+use crate::availability::Availability;
+use crate::endpoints::{GameSources, Source, StoreGame};
+use crate::titles::{normalize_title, TitleMatcher};
+
+/// An account on a game platform that a discord user has linked. Games
+/// are read from platforms with a game source, others are only listed.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(from = "StoredAccount")]
+pub struct Account {
+    pub platform: String,
+    pub id: String,
+}
+impl Account {
+    pub fn new(platform: &str, id: &str) -> Self {
+        Account {
+            platform: platform.to_ascii_lowercase(),
+            id: id.to_string()
+        }
+    }
+    pub fn source(&self) -> Option<Source> {
+        self.platform.parse().ok()
+    }
+    pub fn steam(&self) -> Option<SteamID> {
+        match self.source() {
+            Some(Source::Steam) => self.id.parse::<u64>().ok().map(SteamID::from),
+            _ => None
+        }
+    }
+}
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!{f, "{}: {}", self.platform, self.id}
+    }
+}
+
+// accounts used to be steam ids or free text platforms
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAccount {
+    Current {
+        platform: String,
+        id: String
+    },
+    Legacy(LegacyAccount),
+}
+#[derive(Deserialize)]
+enum LegacyAccount {
+    Steam(u64),
+    Other { platform: String, name: String },
+}
+impl From<StoredAccount> for Account {
+    fn from(stored: StoredAccount) -> Self {
+        match stored {
+            StoredAccount::Current { platform, id } => Account { platform, id },
+            StoredAccount::Legacy(LegacyAccount::Steam(id)) => Account::new("steam", &id.to_string()),
+            StoredAccount::Legacy(LegacyAccount::Other { platform, name }) => Account { platform, id: name },
+        }
+    }
+}
+
+/// A discord user, every platform account they have linked and the games
+/// they own without an api to read them from. Shared across every guild
+/// they are in unless hidden there.
+#[derive(Deserialize,Serialize)]
+#[serde(from = "StoredProfile")]
+pub struct Profile {
+    pub user: UserId,
+    pub accounts: Vec<Account>,
+    pub hidden: BTreeSet<GuildId>,
+    /// Guilds they left, where the profile is hidden until they return.
+    pub left: BTreeSet<GuildId>,
+    pub library: Vec<String>,
+    /// Titles imported from library exports, by platform.
+    pub imports: BTreeMap<String, Vec<String>>,
+    /// Zone their times are read and shown in, UTC when unset.
+    pub timezone: Option<Tz>,
+    /// When they can usually play each week, in their time zone.
+    pub availability: Vec<Availability>,
+}
+impl Profile {
+    pub fn new(user: UserId) -> Self {
+        Profile {
+            user,
+            accounts: Vec::new(),
+            hidden: BTreeSet::new(),
+            left: BTreeSet::new(),
+            library: Vec::new(),
+            imports: BTreeMap::new(),
+            timezone: None,
+            availability: Vec::new()
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.library.is_empty() && self.imports.is_empty()
+            && self.timezone.is_none() && self.availability.is_empty()
+    }
+    pub fn zone(&self) -> Tz {
+        self.timezone.unwrap_or(Tz::UTC)
+    }
+    /// Every title owned without a game source, by hand or imported.
+    pub fn manual_titles(&self) -> impl Iterator<Item = &String> {
+        self.library.iter().chain(self.imports.values().flatten())
+    }
+    pub fn visible_in(&self, gid: GuildId) -> bool {
+        !self.hidden.contains(&gid) && !self.left.contains(&gid)
+    }
+    /// Adds an account, returning false if it was already linked.
+    pub fn link(&mut self, account: Account) -> bool {
+        if self.accounts.contains(&account) {
+            return false;
+        }
+        self.accounts.push(account);
+        true
+    }
+    /// Removes an account, returning false if it was not linked.
+    pub fn unlink(&mut self, account: &Account) -> bool {
+        let before = self.accounts.len();
+        self.accounts.retain(|a| a != account);
+        before != self.accounts.len()
+    }
+    /// Adds a game to the manual library, returning false if a game with
+    /// the same normalized title is already in it.
+    pub fn own(&mut self, title: &str) -> bool {
+        let key = normalize_title(title);
+        if self.library.iter().any(|t| normalize_title(t) == key) {
+            return false;
+        }
+        self.library.push(title.to_string());
+        true
+    }
+    /// Removes a game from the manual library by its normalized title.
+    pub fn disown(&mut self, title: &str) -> bool {
+        let key = normalize_title(title);
+        let before = self.library.len();
+        self.library.retain(|t| normalize_title(t) != key);
+        before != self.library.len()
+    }
+}
+
+// players used to be stored as a single steam id per user
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredProfile {
+    Current {
+        user: UserId,
+        accounts: Vec<Account>,
+        #[serde(default)]
+        hidden: BTreeSet<GuildId>,
+        #[serde(default)]
+        left: BTreeSet<GuildId>,
+        #[serde(default)]
+        library: Vec<String>,
+        #[serde(default)]
+        imports: BTreeMap<String, Vec<String>>,
+        #[serde(default)]
+        timezone: Option<Tz>,
+        #[serde(default)]
+        availability: Vec<Availability>
+    },
+    Legacy(LegacyPlayer),
+}
+#[derive(Deserialize)]
+enum LegacyPlayer {
+    Steam(UserId, u64),
+}
+impl From<StoredProfile> for Profile {
+    fn from(stored: StoredProfile) -> Self {
+        match stored {
+            StoredProfile::Current { user, accounts, hidden, left, library, imports, timezone, availability } =>
+                Profile { user, accounts, hidden, left, library, imports, timezone, availability },
+            StoredProfile::Legacy(LegacyPlayer::Steam(user, id)) => {
+                let mut profile = Profile::new(user);
+                profile.link(Account::new("steam", &id.to_string()));
+                profile
+            },
+        }
+    }
+}
+
+/// Every user's profile, keyed by discord user.
+#[derive(Default,Deserialize,Serialize)]
+#[serde(from = "StoredRegistry")]
+pub struct PlayerRegistry {
+    profiles: HashMap<UserId, Profile>,
+}
+impl PlayerRegistry {
+    pub fn get(&self, user: UserId) -> Option<&Profile> {
+        self.profiles.get(&user)
+    }
+    pub fn get_mut(&mut self, user: UserId) -> Option<&mut Profile> {
+        self.profiles.get_mut(&user)
+    }
+    /// The user's profile, unless they have hidden it in the guild.
+    pub fn in_guild(&self, gid: GuildId, user: UserId) -> Option<&Profile> {
+        self.get(user).filter(|p| p.visible_in(gid))
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Profile> {
+        self.profiles.values()
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Profile> {
+        self.profiles.values_mut()
+    }
+    /// Links an account to the user's profile, creating it if needed, and
+    /// returns false if it was already linked.
+    pub fn link(&mut self, user: UserId, account: Account) -> bool {
+        self.profiles.entry(user)
+            .or_insert_with(|| Profile::new(user))
+            .link(account)
+    }
+    /// Removes one of the user's accounts, or all of them when none is
+    /// given, returning false if nothing was linked. Profiles left empty
+    /// are dropped.
+    pub fn unlink(&mut self, user: UserId, account: Option<&Account>) -> bool {
+        let profile = match self.profiles.get_mut(&user) {
+            Some(p) => p,
+            None => return false
+        };
+        let removed = match account {
+            Some(a) => profile.unlink(a),
+            None => !std::mem::take(&mut profile.accounts).is_empty()
+        };
+        if profile.is_empty() {
+            self.profiles.remove(&user);
+        }
+        removed
+    }
+    /// Adds a game to the user's manual library, creating their profile if
+    /// needed, and returns false if they already own it.
+    pub fn own(&mut self, user: UserId, title: &str) -> bool {
+        self.profiles.entry(user)
+            .or_insert_with(|| Profile::new(user))
+            .own(title)
+    }
+    /// Replaces the titles imported for a platform.
+    pub fn import(&mut self, user: UserId, platform: &str, titles: Vec<String>) {
+        self.profiles.entry(user)
+            .or_insert_with(|| Profile::new(user))
+            .imports.insert(platform.to_string(), titles);
+    }
+    /// Hides the profile of a user who left a guild until they return, or
+    /// drops it when they are in no other guild with the bot, so links of
+    /// people who are gone don't pile up.
+    pub fn member_left(&mut self, user: UserId, gid: GuildId, elsewhere: bool) {
+        if !elsewhere {
+            self.profiles.remove(&user);
+        } else if let Some(profile) = self.profiles.get_mut(&user) {
+            profile.left.insert(gid);
+        }
+    }
+    /// Shows the profile of a user who came back to a guild again.
+    pub fn member_joined(&mut self, user: UserId, gid: GuildId) {
+        if let Some(profile) = self.profiles.get_mut(&user) {
+            profile.left.remove(&gid);
+        }
+    }
+    /// Sets or clears the user's time zone. Profiles left empty are dropped.
+    pub fn set_timezone(&mut self, user: UserId, timezone: Option<Tz>) {
+        self.update(user, |p| p.timezone = timezone);
+    }
+    /// Replaces the user's weekly availability. Profiles left empty are
+    /// dropped.
+    pub fn set_availability(&mut self, user: UserId, availability: Vec<Availability>) {
+        self.update(user, |p| p.availability = availability);
+    }
+    fn update(&mut self, user: UserId, change: impl FnOnce(&mut Profile)) {
+        let profile = self.profiles.entry(user).or_insert_with(|| Profile::new(user));
+        change(profile);
+        if profile.is_empty() {
+            self.profiles.remove(&user);
+        }
+    }
+    /// Removes a game from the user's manual library, returning false if it
+    /// wasn't in there. Profiles left empty are dropped.
+    pub fn disown(&mut self, user: UserId, title: &str) -> bool {
+        let profile = match self.profiles.get_mut(&user) {
+            Some(p) => p,
+            None => return false
+        };
+        let removed = profile.disown(title);
+        if profile.is_empty() {
+            self.profiles.remove(&user);
+        }
+        removed
+    }
+}
+
+// profiles used to be kept separately for every guild
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredRegistry {
+    Current {
+        profiles: HashMap<UserId, Profile>
+    },
+    PerGuild(HashMap<GuildId, Vec<Profile>>),
+}
+impl From<StoredRegistry> for PlayerRegistry {
+    fn from(stored: StoredRegistry) -> Self {
+        match stored {
+            StoredRegistry::Current { profiles } => PlayerRegistry { profiles },
+            StoredRegistry::PerGuild(guilds) => {
+                let mut registry = PlayerRegistry::default();
+                for profile in guilds.into_values().flatten() {
+                    for account in profile.accounts {
+                        registry.link(profile.user, account);
+                    }
+                }
+                registry
+            }
+        }
+    }
+}
+
+/// A game in someone's library, read from a store or declared by hand.
+#[derive(Clone)]
+pub enum Owned {
+    Store(StoreGame),
+    Manual(String),
+}
+impl Owned {
+    pub fn title(&self) -> &str {
+        match self {
+            Owned::Store(game) => &game.name,
+            Owned::Manual(title) => title,
+        }
+    }
+}
+impl fmt::Display for Owned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Owned::Store(game) => write!{f, "{} - {}", game.name, game.url()},
+            Owned::Manual(title) => write!{f, "{}", title},
+        }
+    }
+}
+
+/// One user's games from every library, keyed by canonical title so the
+/// same game on different platforms is listed once. Store games win over
+/// manual entries as they can be linked.
+pub type Library = HashMap<String, Owned>;
+
+pub fn add_to_library(library: &mut Library, matcher: &TitleMatcher, game: Owned) {
+    let key = match &game {
+        Owned::Store(g) => matcher.canonical(&g.name),
+        Owned::Manual(t) => matcher.canonical(t),
+    };
+    match (library.get(&key), &game) {
+        (Some(Owned::Store(_)), _) | (Some(Owned::Manual(_)), Owned::Manual(_)) => {},
+        _ => { library.insert(key, game); }
+    };
+}
+/// The games of the left library that are also in the right one, as store
+/// games when either side has them from a store.
+pub fn find_common(left: Library, right: &Library) -> Library {
+    left.into_iter()
+        .filter_map(|(key, game)| match (game, right.get(&key)) {
+            (_, None) => None,
+            (Owned::Manual(_), Some(Owned::Store(g))) => Some((key, Owned::Store(g.clone()))),
+            (game, Some(_)) => Some((key, game)),
+        })
+        .collect()
+}
+/// Reads the games of every account with a game source into one library
+/// along with the manual titles. Accounts whose games couldn't be read are
+/// returned alongside it.
+pub async fn gather_library(
+    accounts: &[Account],
+    manual: &[String],
+    sources: &<GameSources as TypeMapKey>::Value,
+    matcher: &TitleMatcher<'_>
+) -> (Library, Vec<Account>) {
+    let mut library = Library::new();
+    let mut failed = Vec::new();
+    for account in accounts.iter() {
+        let source = match account.source().and_then(|s| sources.get(&s)) {
+            Some(s) => s,
+            None => continue
+        };
+        match source.lock().await.owned_games(&account.id).await {
+            Ok(games) => {
+                for game in games {
+                    add_to_library(&mut library, matcher, Owned::Store(game));
+                }
+            },
+            Err(_) => failed.push(account.clone())
+        };
+    }
+    for title in manual {
+        add_to_library(&mut library, matcher, Owned::Manual(title.clone()));
+    }
+    (library, failed)
+}
+
+/// The games in every library, sorted by canonical title.
+pub fn common_games(mut libraries: Vec<Library>) -> Vec<Owned> {
+    let first = match libraries.pop() {
+        Some(l) => l,
+        None => return Vec::new()
+    };
+    let mut common: Vec<(String, Owned)> = libraries.iter().fold(first, find_common).into_iter().collect();
+    common.sort_by(|a, b| a.0.cmp(&b.0));
+    common.into_iter().map(|(_, game)| game).collect()
+}
+
+/// Joins lines into blocks of at most `max_len` bytes, so long game lists
+/// can be split over several messages. Lines longer than `max_len` get a
+/// block of their own.
+pub fn line_blocks(lines: &[String], max_len: usize) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut block = String::new();
+    for line in lines {
+        if !block.is_empty() && block.len() + line.len() > max_len {
+            blocks.push(std::mem::take(&mut block));
+        }
+        block.push_str(line);
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steam_game(id: &str, name: &str) -> Owned {
+        Owned::Store(StoreGame { source: Source::Steam, id: id.to_string(), name: name.to_string() })
+    }
+    fn library(matcher: &TitleMatcher, games: Vec<Owned>) -> Library {
+        let mut library = Library::new();
+        for game in games {
+            add_to_library(&mut library, matcher, game);
+        }
+        library
+    }
+    fn titles(games: &[Owned]) -> Vec<String> {
+        games.iter().map(|g| g.to_string()).collect()
+    }
+
+    #[test]
+    fn links_into_one_profile() {
+        let mut registry = PlayerRegistry::default();
+        assert!(registry.link(UserId(1), Account::new("Steam", "76561197960287930")));
+        assert!(!registry.link(UserId(1), Account::new("steam", "76561197960287930")));
+        assert!(registry.link(UserId(1), Account::new("origin", "someone")));
+        let profile = registry.get(UserId(1)).unwrap();
+        assert_eq!(profile.accounts.len(), 2);
+        assert_eq!(profile.accounts[0].steam(), Some(SteamID::from(76561197960287930)));
+        // origin has no game source, its accounts are only listed
+        assert_eq!(profile.accounts[1].source(), None);
+
+        assert!(registry.own(UserId(1), "Rocket League"));
+        assert!(!registry.own(UserId(1), "rocket league®"));
+        assert!(registry.unlink(UserId(1), None));
+        assert!(registry.get(UserId(1)).is_some());
+        assert!(registry.disown(UserId(1), "ROCKET LEAGUE"));
+        assert!(registry.get(UserId(1)).is_none());
+        assert!(!registry.unlink(UserId(1), None));
+    }
+
+    #[test]
+    fn hides_profiles_per_guild() {
+        let mut registry = PlayerRegistry::default();
+        registry.link(UserId(1), Account::new("origin", "someone"));
+        registry.get_mut(UserId(1)).unwrap().hidden.insert(GuildId(10));
+        assert!(registry.in_guild(GuildId(10), UserId(1)).is_none());
+        assert!(registry.in_guild(GuildId(20), UserId(1)).is_some());
+    }
+
+    #[test]
+    fn hides_or_drops_profiles_of_members_who_leave() {
+        let mut registry = PlayerRegistry::default();
+        registry.link(UserId(1), Account::new("steam", "76561197960287930"));
+        registry.link(UserId(2), Account::new("steam", "76561197960287931"));
+        registry.member_left(UserId(1), GuildId(10), true);
+        assert!(registry.in_guild(GuildId(10), UserId(1)).is_none());
+        assert!(registry.in_guild(GuildId(20), UserId(1)).is_some());
+        registry.member_joined(UserId(1), GuildId(10));
+        assert!(registry.in_guild(GuildId(10), UserId(1)).is_some());
+        registry.member_left(UserId(2), GuildId(10), false);
+        assert!(registry.get(UserId(2)).is_none());
+    }
+
+    #[test]
+    fn keeps_profiles_with_only_a_time_zone() {
+        let mut registry = PlayerRegistry::default();
+        registry.set_timezone(UserId(1), Some(chrono_tz::Europe::Berlin));
+        assert_eq!(registry.get(UserId(1)).unwrap().zone(), chrono_tz::Europe::Berlin);
+        let saved = serde_json::to_string(&registry).unwrap();
+        let loaded: PlayerRegistry = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.get(UserId(1)).unwrap().timezone, Some(chrono_tz::Europe::Berlin));
+        registry.set_timezone(UserId(1), None);
+        assert!(registry.get(UserId(1)).is_none());
+    }
+
+    #[test]
+    fn loads_legacy_saves() {
+        let per_guild = r#"{
+            "10": [{"Steam": [1, 76561197960287930]}],
+            "20": [{"user": 1, "accounts": [{"Other": {"platform": "origin", "name": "someone"}}]}]
+        }"#;
+        let registry: PlayerRegistry = serde_json::from_str(per_guild).unwrap();
+        let profile = registry.get(UserId(1)).unwrap();
+        assert_eq!(profile.accounts.len(), 2);
+        assert!(profile.accounts.contains(&Account::new("steam", "76561197960287930")));
+        assert!(profile.accounts.contains(&Account::new("origin", "someone")));
+    }
+
+    #[test]
+    fn finds_games_common_to_every_library() {
+        let aliases = BTreeMap::new();
+        let matcher = TitleMatcher::new(&aliases);
+        let first = library(&matcher, vec![
+            steam_game("620", "Portal 2"),
+            Owned::Manual("Rocket League".to_string()),
+            Owned::Manual("Valheim".to_string()),
+        ]);
+        let second = library(&matcher, vec![
+            Owned::Manual("portal 2".to_string()),
+            steam_game("252950", "Rocket League®"),
+            Owned::Manual("Valheim".to_string()),
+        ]);
+        let third = library(&matcher, vec![
+            Owned::Manual("Portal 2".to_string()),
+            Owned::Manual("Rocket League".to_string()),
+        ]);
+        let common = common_games(vec![first, second, third]);
+        assert_eq!(titles(&common), vec![
+            "Portal 2 - https://store.steampowered.com/app/620/",
+            "Rocket League® - https://store.steampowered.com/app/252950/",
+        ]);
+        assert!(common_games(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn splits_lines_into_blocks_without_dropping_any() {
+        let lines: Vec<String> = ["aaaa\n", "bbbb\n", "cccc\n", "a very long line\n"].iter().map(|l| l.to_string()).collect();
+        let blocks = line_blocks(&lines, 10);
+        assert_eq!(blocks, vec!["aaaa\nbbbb\n", "cccc\n", "a very long line\n"]);
+        assert_eq!(blocks.concat(), lines.concat());
+        assert!(line_blocks(&[], 10).is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+use serenity::model::id::UserId;
+
+/// Most answers a poll can have, one per voting reaction.
+pub const MAX_ANSWERS: usize = 10;
+
+/// A question with answers people vote on. Votes are counted once the poll
+/// is closed.
+pub struct StrawPoll {
+    pub creator: UserId,
+    pub question: String,
+    pub answers: Vec<String>,
+    pub answerers: Vec<usize>,
+}
+impl StrawPoll {
+    pub fn new<Q>(creator: UserId, question: Q) -> Self where Q: Into<String> {
+        StrawPoll {
+            creator,
+            question: question.into(),
+            answers: Vec::new(),
+            answerers: Vec::new()
+        }
+    }
+    pub fn set_question<Q>(&mut self, question: Q) where Q: Into<String> {
+        self.question = question.into();
+    }
+    /// Adds an answer, failing once the poll has as many as it can take.
+    pub fn add_answer<A>(&mut self, answer: A) -> Result<(), String> where A: Into<String> {
+        if self.answers.len() >= MAX_ANSWERS {
+            return Err(format!{"Polls can have at most {} answers.", MAX_ANSWERS});
+        }
+        self.answers.push( answer.into() );
+        Ok(())
+    }
+    pub fn remove_answer(&mut self, answer: usize) -> Option<()> {
+        if self.answers.len() <= answer  {
+            return None;
+        }
+        self.answers.remove(answer);
+        Some( () )
+    }
+    /// Whether the poll has enough answers to be posted.
+    pub fn is_ready(&self) -> bool {
+        (2..=MAX_ANSWERS).contains(&self.answers.len())
+    }
+    /// Records the number of votes for each answer, in order. Missing
+    /// counts are zero and extra ones are ignored.
+    pub fn close(&mut self, counts: &[usize]) {
+        self.answerers = (0..self.answers.len())
+            .map(|i| counts.get(i).copied().unwrap_or(0))
+            .collect();
+    }
+    /// Each answer with its votes, most voted first.
+    pub fn results(&self) -> Vec<(&str, usize)> {
+        let mut results: Vec<(&str, usize)> = self.answers.iter()
+            .map(|a| a.as_str())
+            .zip(self.answerers.iter().copied().chain(std::iter::repeat(0)))
+            .collect();
+        results.sort_by_key(|r| std::cmp::Reverse(r.1));
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_answers() {
+        let mut poll = StrawPoll::new(UserId(1), "What tonight?");
+        assert!(poll.add_answer("Deep Rock").is_ok());
+        assert!(!poll.is_ready());
+        for i in 1..MAX_ANSWERS {
+            poll.add_answer(format!{"Answer {}", i}).unwrap();
+        }
+        assert!(poll.is_ready());
+        assert!(poll.add_answer("One too many").is_err());
+        assert_eq!(poll.answers.len(), MAX_ANSWERS);
+    }
+
+    #[test]
+    fn edits_question_and_answers() {
+        let mut poll = StrawPoll::new(UserId(1), "What tonight?");
+        poll.set_question("What this weekend?");
+        assert_eq!(poll.question, "What this weekend?");
+        poll.add_answer("Deep Rock").unwrap();
+        poll.add_answer("Valheim").unwrap();
+        assert!(poll.remove_answer(2).is_none());
+        assert!(poll.remove_answer(0).is_some());
+        assert_eq!(poll.answers, vec!["Valheim"]);
+    }
+
+    #[test]
+    fn tallies_results() {
+        let mut poll = StrawPoll::new(UserId(1), "What tonight?");
+        for answer in ["Deep Rock", "Valheim", "Portal 2"] {
+            poll.add_answer(answer).unwrap();
+        }
+        assert_eq!(poll.results(), vec![("Deep Rock", 0), ("Valheim", 0), ("Portal 2", 0)]);
+        poll.close(&[1, 3]);
+        assert_eq!(poll.answerers, vec![1, 3, 0]);
+        assert_eq!(poll.results(), vec![("Valheim", 3), ("Deep Rock", 1), ("Portal 2", 0)]);
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+use serenity::model::id::{GuildId, UserId};
+
+/// Longest time span or delay a guild can set, in seconds.
+pub const MAX_SECONDS: u32 = 24 * 60 * 60;
+/// Most uses a guild can allow per time span.
+pub const MAX_USES: u32 = 100;
+
+/// Commands that share a rate limit.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Bucket {
+    /// Adding suggestions.
+    Suggest,
+    /// Commands that look users up on steam.
+    Steam,
+}
+impl Bucket {
+    pub fn parse(name: &str) -> Option<Bucket> {
+        match name.to_ascii_lowercase().as_str() {
+            "suggest" => Some(Bucket::Suggest),
+            "steam" => Some(Bucket::Steam),
+            _ => None
+        }
+    }
+}
+
+/// How often a user may use the commands of a bucket: at most `limit`
+/// times per `time_span` seconds and `delay` seconds apart.
+#[derive(Clone,Copy,Debug,PartialEq,Deserialize,Serialize)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub time_span: u32,
+    pub delay: u32,
+}
+impl RateLimit {
+    /// Checks the values are within what guilds may set.
+    pub fn new(limit: u32, time_span: u32, delay: u32) -> Result<Self, String> {
+        if !(1..=MAX_USES).contains(&limit) {
+            return Err(format!{"The limit should be between 1 and {} uses.", MAX_USES});
+        }
+        if !(1..=MAX_SECONDS).contains(&time_span) || delay > MAX_SECONDS {
+            return Err(format!{"Time spans and delays can be at most {} seconds.", MAX_SECONDS});
+        }
+        Ok(RateLimit { limit, time_span, delay })
+    }
+}
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} per {}s, {}s apart", self.limit, self.time_span, self.delay)
+    }
+}
+
+/// A guild's rate limit for each bucket.
+#[derive(Clone,Debug,Deserialize,Serialize)]
+#[serde(default)]
+pub struct RateLimits {
+    pub suggest: RateLimit,
+    pub steam: RateLimit,
+}
+impl RateLimits {
+    pub fn get(&self, bucket: Bucket) -> RateLimit {
+        match bucket {
+            Bucket::Suggest => self.suggest,
+            Bucket::Steam => self.steam,
+        }
+    }
+    pub fn set(&mut self, bucket: Bucket, limit: RateLimit) {
+        match bucket {
+            Bucket::Suggest => self.suggest = limit,
+            Bucket::Steam => self.steam = limit,
+        }
+    }
+}
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            suggest: RateLimit { limit: 5, time_span: 60, delay: 5 },
+            steam: RateLimit { limit: 4, time_span: 120, delay: 10 }
+        }
+    }
+}
+impl fmt::Display for RateLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "suggest {} | steam {}", self.suggest, self.steam)
+    }
+}
+
+/// Recent uses of each bucket by each user, per guild.
+#[derive(Default)]
+pub struct RateLimiter {
+    uses: HashMap<(GuildId, UserId, Bucket), VecDeque<DateTime<Utc>>>,
+}
+impl RateLimiter {
+    /// Counts a use at `now`, or returns how long until the user may use
+    /// the bucket again without counting it.
+    pub fn hit(&mut self, gid: GuildId, user: UserId, bucket: Bucket, limit: &RateLimit, now: DateTime<Utc>) -> Result<(), Duration> {
+        let span = Duration::seconds(limit.time_span as i64);
+        let uses = self.uses.entry((gid, user, bucket)).or_default();
+        while uses.front().is_some_and(|used| now - *used >= span) {
+            uses.pop_front();
+        }
+        let mut wait = Duration::zero();
+        if let Some(last) = uses.back() {
+            wait = wait.max(*last + Duration::seconds(limit.delay as i64) - now);
+        }
+        if uses.len() >= limit.limit as usize {
+            let freed = uses.len() + 1 - limit.limit as usize;
+            wait = wait.max(uses[freed - 1] + span - now);
+        }
+        if wait > Duration::zero() {
+            return Err(wait);
+        }
+        uses.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.ymd(2024, 5, 15).and_hms(18, 0, 0) + Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn limits_uses_per_time_span_and_delay() {
+        let mut limiter = RateLimiter::default();
+        let limit = RateLimit::new(2, 60, 5).unwrap();
+        let hit = |l: &mut RateLimiter, s| l.hit(GuildId(1), UserId(1), Bucket::Suggest, &limit, at(s));
+        assert!(hit(&mut limiter, 0).is_ok());
+        assert_eq!(hit(&mut limiter, 2), Err(Duration::seconds(3)));
+        assert!(hit(&mut limiter, 10).is_ok());
+        assert_eq!(hit(&mut limiter, 20), Err(Duration::seconds(40)));
+        assert!(hit(&mut limiter, 60).is_ok());
+    }
+
+    #[test]
+    fn keeps_guilds_users_and_buckets_apart() {
+        let mut limiter = RateLimiter::default();
+        let limit = RateLimit::new(1, 60, 0).unwrap();
+        assert!(limiter.hit(GuildId(1), UserId(1), Bucket::Steam, &limit, at(0)).is_ok());
+        assert!(limiter.hit(GuildId(1), UserId(1), Bucket::Steam, &limit, at(1)).is_err());
+        assert!(limiter.hit(GuildId(2), UserId(1), Bucket::Steam, &limit, at(1)).is_ok());
+        assert!(limiter.hit(GuildId(1), UserId(2), Bucket::Steam, &limit, at(1)).is_ok());
+        assert!(limiter.hit(GuildId(1), UserId(1), Bucket::Suggest, &limit, at(1)).is_ok());
+    }
+
+    #[test]
+    fn rejects_limits_out_of_range() {
+        assert!(RateLimit::new(0, 60, 0).is_err());
+        assert!(RateLimit::new(MAX_USES + 1, 60, 0).is_err());
+        assert!(RateLimit::new(5, 0, 0).is_err());
+        assert!(RateLimit::new(5, 60, MAX_SECONDS + 1).is_err());
+    }
+}
@@ -0,0 +1,471 @@
+use std::fmt;
+use std::str::FromStr;
+use std::collections::{BTreeSet, HashMap};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use serenity::model::id::UserId;
+
+use crate::endpoints::{steam, Source, StoreGame};
+use crate::tags::normalize_tag;
+
+#[derive(Deserialize,Serialize)]
+pub enum SuggestedGame {
+    #[serde(alias = "Steam")]
+    Store(StoreGame),
+    PlainText(TextSuggestion)
+}
+impl<'a> SuggestedGame {
+    pub fn title(&'a self) -> &'a str {
+        use self::SuggestedGame::*;
+        match self {
+            Store(app) => app.name.as_ref(),
+            PlainText(app) => app.title.as_ref()
+        }
+    }
+}
+impl PartialEq for SuggestedGame {
+    fn eq(&self, other: &SuggestedGame) -> bool {
+        use self::SuggestedGame::*;
+        match self {
+            Store(s_app) => match other {
+                Store(o_app) => s_app == o_app,
+                PlainText(o_app) => s_app.name == o_app.title,
+            },
+            PlainText(s_app) => match other {
+                Store(o_app) => s_app.title == o_app.name,
+                PlainText(o_app) => s_app.title == o_app.title,
+            },
+        }
+    }
+}
+impl fmt::Display for SuggestedGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::SuggestedGame::*;
+        match self {
+            Store(app) => write!(f, "{}: {} - {}", app.source, app.name, app.url()),
+            PlainText(app) => app.fmt(f),
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Deserialize,Serialize)]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+/// Where a suggestion is in its life, from waiting to be played to finished.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Deserialize,Serialize)]
+pub enum SuggestionState {
+    #[default]
+    Backlog,
+    Played,
+    Completed,
+    Dropped,
+}
+impl SuggestionState {
+    /// Only backlog suggestions are listed and picked by default.
+    pub fn is_active(self) -> bool {
+        self == SuggestionState::Backlog
+    }
+    pub fn can_become(self, next: SuggestionState) -> bool {
+        use self::SuggestionState::*;
+        matches!(
+            (self, next),
+            (Backlog, Played) | (Backlog, Completed) | (Backlog, Dropped)
+                | (Played, Completed) | (Played, Dropped) | (Played, Backlog)
+                | (Completed, Played) | (Completed, Backlog)
+                | (Dropped, Played) | (Dropped, Backlog)
+        )
+    }
+}
+impl FromStr for SuggestionState {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "backlog" => Ok(SuggestionState::Backlog),
+            "played" => Ok(SuggestionState::Played),
+            "completed" | "done" => Ok(SuggestionState::Completed),
+            "dropped" => Ok(SuggestionState::Dropped),
+            _ => Err(format!{"Invalid state '{}'. Try 'backlog', 'played', 'completed' or 'dropped'.", s})
+        }
+    }
+}
+impl fmt::Display for SuggestionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!{"{:?}", self}.to_ascii_lowercase())
+    }
+}
+
+/// A change of state, who made it and when.
+#[derive(Deserialize,Serialize)]
+pub struct Transition {
+    pub state: SuggestionState,
+    pub at: DateTime<Utc>,
+    pub by: UserId,
+}
+
+/// A suggested game along with who suggested it and when. The id is unique
+/// within a guild and is never reused, even after removal.
+#[derive(Deserialize,Serialize)]
+pub struct Suggestion {
+    pub id: u32,
+    pub user: UserId,
+    pub created: DateTime<Utc>,
+    pub game: SuggestedGame,
+    #[serde(default)]
+    pub votes: HashMap<UserId, Vote>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub state: SuggestionState,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+impl<'a> Suggestion {
+    pub fn title(&'a self) -> &'a str {
+        self.game.title()
+    }
+    pub fn user(&self) -> UserId {
+        self.user
+    }
+    /// Records a user's vote, replacing any earlier one, or clears it with `None`.
+    pub fn vote(&mut self, user: UserId, vote: Option<Vote>) {
+        match vote {
+            Some(v) => { self.votes.insert(user, v); },
+            None => { self.votes.remove(&user); },
+        };
+    }
+    /// Moves the suggestion to a new state, recording when and by whom.
+    pub fn transition(&mut self, next: SuggestionState, by: UserId) -> Result<(), String> {
+        if !self.state.can_become(next) {
+            return Err(format!{"#{} cannot go from {} to {}.", self.id, self.state, next});
+        }
+        self.state = next;
+        self.transitions.push(Transition { state: next, at: Utc::now(), by });
+        Ok(())
+    }
+    /// Whether the suggestion carries every one of the normalized tags.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|t| self.tags.contains(t))
+    }
+    /// Upvotes minus downvotes.
+    pub fn score(&self) -> i32 {
+        self.votes.values().map(|v| match v {
+            Vote::Up => 1,
+            Vote::Down => -1,
+        }).sum()
+    }
+}
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.id, self.game)?;
+        if !self.state.is_active() {
+            write!(f, " [{}]", self.state)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug,Default,Hash,Deserialize,Serialize)]
+pub struct TextSuggestion {
+    pub title: String,
+    pub genre: Option<String>,
+    pub url: Option<String>,
+}
+impl fmt::Display for TextSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Title: {}", self.title)?;
+        if let Some(genre) = &self.genre {
+            write!(f, " | Genre: {}", genre)?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, " | Url: {}", url)?;
+        }
+        Ok(())
+    }
+}
+
+/// All suggestions for a single guild, kept in the order they were added.
+#[derive(Default,Deserialize,Serialize)]
+#[serde(from = "StoredBook")]
+pub struct SuggestionBook {
+    next_id: u32,
+    suggestions: Vec<Suggestion>,
+}
+impl SuggestionBook {
+    pub fn iter(&self) -> std::slice::Iter<'_, Suggestion> {
+        self.suggestions.iter()
+    }
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Suggestion> {
+        self.suggestions.iter_mut()
+    }
+    /// Adds a suggestion under the next free id and returns it, unless the
+    /// game has been suggested already. A plain suggestion's genre becomes
+    /// its first tag.
+    pub fn add(&mut self, user: UserId, game: SuggestedGame) -> Result<&mut Suggestion, String> {
+        if self.duplicate_of(&game).is_some() {
+            return Err("This game has been suggested already, thanks!".to_string());
+        }
+        Ok(self.insert(user, game))
+    }
+    fn insert(&mut self, user: UserId, game: SuggestedGame) -> &mut Suggestion {
+        self.next_id += 1;
+        let mut tags = BTreeSet::new();
+        if let SuggestedGame::PlainText(TextSuggestion { genre: Some(genre), .. }) = &game {
+            tags.extend(normalize_tag(genre));
+        }
+        self.suggestions.push(Suggestion {
+            id: self.next_id,
+            user,
+            created: Utc::now(),
+            game,
+            votes: HashMap::new(),
+            tags,
+            state: SuggestionState::Backlog,
+            transitions: Vec::new()
+        });
+        self.suggestions.last_mut().expect("suggestion just added")
+    }
+    /// The existing suggestion of the same game: the same store game, or
+    /// any game with the title of a plain suggestion.
+    pub fn duplicate_of(&self, game: &SuggestedGame) -> Option<&Suggestion> {
+        match game {
+            SuggestedGame::PlainText(app) => self.find_by_title(&app.title),
+            SuggestedGame::Store(_) => self.suggestions.iter().find(|s| s.game == *game),
+        }
+    }
+    pub fn get(&self, id: u32) -> Option<&Suggestion> {
+        self.suggestions.iter().find(|s| s.id == id)
+    }
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Suggestion> {
+        self.suggestions.iter_mut().find(|s| s.id == id)
+    }
+    /// Finds a suggestion by case-insensitive title.
+    pub fn find_by_title(&self, title: &str) -> Option<&Suggestion> {
+        let title = title.to_ascii_lowercase();
+        self.suggestions.iter().find(|s| s.title().to_ascii_lowercase() == title)
+    }
+    /// Finds a store suggestion by its id in the store or case-insensitive
+    /// name, without needing the store's catalog.
+    pub fn find_in_store(&self, source: Source, target: &str) -> Option<&Suggestion> {
+        self.suggestions.iter().find(|s| matches!(
+            &s.game,
+            SuggestedGame::Store(app) if app.source == source && (app.id == target || app.name.eq_ignore_ascii_case(target))
+        ))
+    }
+    /// The number of the user's suggestions still waiting to be played.
+    pub fn active_count(&self, user: UserId) -> usize {
+        self.suggestions.iter().filter(|s| s.user == user && s.state.is_active()).count()
+    }
+    /// The suggestions passing a filter, in the order they were added.
+    pub fn list(&self, filter: &Filter) -> Vec<&Suggestion> {
+        self.suggestions.iter().filter(|s| filter.matches(s)).collect()
+    }
+    /// Removes a suggestion without disturbing the order of the others.
+    /// Only its suggester or a moderator can remove it.
+    pub fn remove(&mut self, id: u32, by: UserId, moderator: bool) -> Result<Suggestion, String> {
+        let idx = match self.suggestions.iter().position(|s| s.id == id) {
+            Some(i) => i,
+            None => return Err("No suggestion with that id.".to_string())
+        };
+        if self.suggestions[idx].user != by && !moderator {
+            return Err("Found suggestion but cannot be removed by you.".to_string());
+        }
+        Ok(self.suggestions.remove(idx))
+    }
+}
+
+/// On-disk layouts of a guild's suggestions. Older saves are a bare list
+/// without ids or timestamps, which are assigned when loaded.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredBook {
+    Current {
+        next_id: u32,
+        suggestions: Vec<Suggestion>
+    },
+    Legacy(Vec<LegacySuggestion>),
+}
+#[derive(Deserialize)]
+enum LegacySuggestion {
+    Steam(UserId, steam::App),
+    PlainText(UserId, TextSuggestion)
+}
+impl From<StoredBook> for SuggestionBook {
+    fn from(stored: StoredBook) -> Self {
+        match stored {
+            StoredBook::Current { next_id, suggestions } => SuggestionBook { next_id, suggestions },
+            StoredBook::Legacy(legacy) => {
+                let mut book = SuggestionBook::default();
+                // kept as saved, even if the same game was suggested twice
+                for suggestion in legacy {
+                    match suggestion {
+                        LegacySuggestion::Steam(user, app) => book.insert(user, SuggestedGame::Store(app.into())),
+                        LegacySuggestion::PlainText(user, app) => book.insert(user, SuggestedGame::PlainText(app)),
+                    };
+                }
+                book
+            }
+        }
+    }
+}
+
+/// Parses a suggestion id written as `#12`.
+pub fn parse_id(arg: &str) -> Option<u32> {
+    arg.strip_prefix('#')?.parse::<u32>().ok()
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum KindFilter {
+    Store(Source),
+    Plain,
+}
+impl FromStr for KindFilter {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "plain" => Ok(KindFilter::Plain),
+            kind => match kind.parse::<Source>() {
+                Ok(source) => Ok(KindFilter::Store(source)),
+                Err(_) => Err(format!{"Invalid type '{}'. Try 'plain' or 'steam'.", s})
+            }
+        }
+    }
+}
+impl fmt::Display for KindFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KindFilter::Store(source) => write!(f, "{}", source.to_string().to_ascii_lowercase()),
+            KindFilter::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+/// Which suggestions are listed. Only active ones by default, unless a
+/// state is asked for or `all` is set.
+#[derive(Clone,Debug,Default)]
+pub struct Filter {
+    pub kind: Option<KindFilter>,
+    pub genre: Option<String>,
+    pub tags: Vec<String>,
+    pub state: Option<SuggestionState>,
+    pub all: bool,
+}
+impl Filter {
+    pub fn matches(&self, suggestion: &Suggestion) -> bool {
+        let kind = match (self.kind, &suggestion.game) {
+            (None, _) => true,
+            (Some(KindFilter::Store(source)), SuggestedGame::Store(app)) => app.source == source,
+            (Some(KindFilter::Plain), SuggestedGame::PlainText(_)) => true,
+            _ => false
+        };
+        // store suggestions carry their store genres as tags
+        let genre = match (&self.genre, &suggestion.game) {
+            (None, _) => true,
+            (Some(genre), SuggestedGame::PlainText(app)) => app.genre.as_ref()
+                .is_some_and(|g| g.to_ascii_lowercase().contains(genre)),
+            (Some(genre), SuggestedGame::Store(_)) => suggestion.tags.iter().any(|t| t.contains(genre)),
+        };
+        let state = match self.state {
+            Some(state) => suggestion.state == state,
+            None => self.all || suggestion.state.is_active(),
+        };
+        kind && genre && state && suggestion.has_tags(&self.tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(title: &str, genre: Option<&str>) -> SuggestedGame {
+        SuggestedGame::PlainText(TextSuggestion {
+            title: title.to_string(),
+            genre: genre.map(|g| g.to_string()),
+            url: None
+        })
+    }
+    fn store(source: Source, id: &str, name: &str) -> SuggestedGame {
+        SuggestedGame::Store(StoreGame { source, id: id.to_string(), name: name.to_string() })
+    }
+
+    #[test]
+    fn adds_under_new_ids_and_rejects_duplicates() {
+        let mut book = SuggestionBook::default();
+        assert_eq!(book.add(UserId(1), plain("Valheim", Some("Survival"))).unwrap().id, 1);
+        assert_eq!(book.get(1).unwrap().tags.iter().collect::<Vec<_>>(), vec!["survival"]);
+        assert!(book.add(UserId(2), plain("valheim", None)).is_err());
+        assert!(book.add(UserId(2), store(Source::Steam, "892970", "Valheim")).is_err());
+        assert!(book.add(UserId(2), store(Source::Steam, "620", "Portal 2")).is_ok());
+        assert!(book.add(UserId(2), store(Source::Steam, "620", "Portal 2")).is_err());
+        assert!(book.add(UserId(2), store(Source::Steam, "105600", "Terraria")).is_ok());
+        // ids are never reused
+        book.remove(3, UserId(2), false).unwrap();
+        assert_eq!(book.add(UserId(1), plain("Deep Rock Galactic", None)).unwrap().id, 4);
+        assert_eq!(book.active_count(UserId(1)), 2);
+    }
+
+    #[test]
+    fn removes_only_own_unless_moderator() {
+        let mut book = SuggestionBook::default();
+        book.add(UserId(1), plain("Valheim", None)).unwrap();
+        book.add(UserId(1), plain("Portal 2", None)).unwrap();
+        book.add(UserId(1), plain("Deep Rock Galactic", None)).unwrap();
+        assert!(book.remove(2, UserId(2), false).is_err());
+        assert!(book.remove(9, UserId(1), false).is_err());
+        assert_eq!(book.remove(2, UserId(2), true).unwrap().title(), "Portal 2");
+        assert_eq!(book.remove(1, UserId(1), false).unwrap().title(), "Valheim");
+        assert!(book.get(1).is_none());
+        assert_eq!(book.iter().map(|s| s.id).collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn lists_by_filter() {
+        let mut book = SuggestionBook::default();
+        book.add(UserId(1), plain("Valheim", Some("Survival"))).unwrap();
+        book.add(UserId(1), store(Source::Steam, "620", "Portal 2")).unwrap()
+            .tags.insert("co-op".to_string());
+        book.add(UserId(2), plain("Deep Rock Galactic", Some("Shooter"))).unwrap()
+            .transition(SuggestionState::Played, UserId(2)).unwrap();
+        let titles = |filter: &Filter| book.list(filter).iter().map(|s| s.title()).collect::<Vec<_>>();
+
+        assert_eq!(titles(&Filter::default()), vec!["Valheim", "Portal 2"]);
+        assert_eq!(titles(&Filter { all: true, ..Filter::default() }).len(), 3);
+        assert_eq!(titles(&Filter { state: Some(SuggestionState::Played), ..Filter::default() }), vec!["Deep Rock Galactic"]);
+        assert_eq!(titles(&Filter { kind: Some(KindFilter::Plain), ..Filter::default() }), vec!["Valheim"]);
+        assert_eq!(titles(&Filter { kind: Some("steam".parse().unwrap()), ..Filter::default() }), vec!["Portal 2"]);
+        assert!("origin".parse::<KindFilter>().is_err());
+        assert_eq!(titles(&Filter { genre: Some("surv".to_string()), ..Filter::default() }), vec!["Valheim"]);
+        assert_eq!(titles(&Filter { tags: vec!["co-op".to_string()], ..Filter::default() }), vec!["Portal 2"]);
+    }
+
+    #[test]
+    fn finds_by_title_and_store() {
+        let mut book = SuggestionBook::default();
+        book.add(UserId(1), store(Source::Steam, "620", "Portal 2")).unwrap();
+        assert_eq!(book.find_by_title("portal 2").unwrap().id, 1);
+        assert_eq!(book.find_in_store(Source::Steam, "620").unwrap().id, 1);
+        assert_eq!(book.find_in_store(Source::Steam, "PORTAL 2").unwrap().id, 1);
+        assert!(book.find_in_store(Source::Steam, "105600").is_none());
+        assert_eq!(parse_id("#12"), Some(12));
+        assert_eq!(parse_id("12"), None);
+    }
+
+    #[test]
+    fn loads_legacy_saves() {
+        let legacy = r#"[
+            {"Steam": [1, {"id": 620, "name": "Portal 2"}]},
+            {"PlainText": [2, {"title": "Valheim", "genre": "Survival", "url": null}]},
+            {"PlainText": [2, {"title": "valheim", "genre": null, "url": null}]}
+        ]"#;
+        let book: SuggestionBook = serde_json::from_str(legacy).unwrap();
+        assert_eq!(book.iter().map(|s| s.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(book.get(1).unwrap().game == store(Source::Steam, "620", "Portal 2"));
+        assert_eq!(book.get(2).unwrap().user, UserId(2));
+
+        let saved = serde_json::to_string(&book).unwrap();
+        let reloaded: SuggestionBook = serde_json::from_str(&saved).unwrap();
+        assert_eq!(reloaded.iter().count(), 3);
+    }
+}
@@ -0,0 +1,42 @@
+use crate::endpoints::steam::AppDetails;
+
+/// Spellings that are folded into a single tag, after normalizing.
+const TAG_ALIASES: [(&str, &str); 12] = [
+    ("coop", "co-op"),
+    ("cooperative", "co-op"),
+    ("online-co-op", "co-op"),
+    ("lan-co-op", "co-op"),
+    ("shared/split-screen-co-op", "co-op"),
+    ("multi-player", "multiplayer"),
+    ("online-pvp", "pvp"),
+    ("lan-pvp", "pvp"),
+    ("shared/split-screen-pvp", "pvp"),
+    ("single-player", "singleplayer"),
+    ("fps", "shooter"),
+    ("massively-multiplayer", "mmo"),
+];
+
+/// Lowercases a tag and joins its words with dashes, so "Online Co-op",
+/// "co op" and "coop" all end up as "co-op". Empty tags are dropped.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '_')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+    if tag.is_empty() {
+        return None;
+    }
+    match TAG_ALIASES.iter().find(|(alias, _)| *alias == tag) {
+        Some((_, canonical)) => Some(canonical.to_string()),
+        None => Some(tag)
+    }
+}
+
+/// Normalized tags for a steam app's store genres and categories.
+pub fn steam_tags(details: &AppDetails) -> Vec<String> {
+    details.genres.iter()
+        .chain(details.categories.iter())
+        .filter_map(|d| normalize_tag(&d.description))
+        .collect()
+}
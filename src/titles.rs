@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+/// Trailing words that only name an edition of a game, after normalizing.
+const EDITION_SUFFIXES: [&str; 12] = [
+    "game of the year edition",
+    "goty edition",
+    "goty",
+    "definitive edition",
+    "complete edition",
+    "deluxe edition",
+    "gold edition",
+    "enhanced edition",
+    "anniversary edition",
+    "standard edition",
+    "special edition",
+    "remastered",
+];
+
+/// Folds a game title into a form that matches across stores and how
+/// people type it: lowercase, without trademark signs, apostrophes or
+/// punctuation, so "Tom Clancy's Rainbow Six® Siege" and
+/// "tom clancys rainbow six: siege" are the same game.
+pub fn normalize_title(title: &str) -> String {
+    title.chars()
+        .filter(|c| !matches!(c, '™' | '®' | '©' | '\'' | '’'))
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// A normalized title without its edition suffixes, so "Counter-Strike 2
+/// Deluxe Edition" is "counter strike 2". Titles that are only a suffix
+/// are kept.
+pub fn base_title(title: &str) -> String {
+    without_editions(normalize_title(title))
+}
+
+fn without_editions(mut key: String) -> String {
+    // only whole words, so "bigoty" keeps its "goty"
+    while let Some(stripped) = EDITION_SUFFIXES.iter()
+        .find_map(|s| key.strip_suffix(s).filter(|k| k.ends_with(' ')).map(|k| k.trim_end().to_string()))
+        .filter(|k| !k.is_empty()) {
+        key = stripped;
+    }
+    key
+}
+
+/// Maps titles from any source onto one canonical game: normalized, with
+/// edition suffixes dropped and a guild's aliases applied.
+pub struct TitleMatcher<'a> {
+    aliases: &'a BTreeMap<String, String>,
+}
+impl<'a> TitleMatcher<'a> {
+    pub fn new(aliases: &'a BTreeMap<String, String>) -> Self {
+        TitleMatcher { aliases }
+    }
+    /// Aliases saved before their games had editions stripped are
+    /// stripped here, so they still match store titles.
+    pub fn canonical(&self, title: &str) -> String {
+        let key = normalize_title(title);
+        if let Some(alias) = self.aliases.get(&key) {
+            return without_editions(alias.clone());
+        }
+        let key = without_editions(key);
+        match self.aliases.get(&key) {
+            Some(alias) => without_editions(alias.clone()),
+            None => key
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_store_spellings() {
+        let aliases = BTreeMap::new();
+        let matcher = TitleMatcher::new(&aliases);
+        assert_eq!(matcher.canonical("Rocket League®"), matcher.canonical("rocket league"));
+        assert_eq!(matcher.canonical("Tom Clancy's Rainbow Six® Siege"), "tom clancys rainbow six siege");
+        assert_eq!(
+            matcher.canonical("The Witcher 3: Wild Hunt - Game of the Year Edition"),
+            matcher.canonical("The Witcher 3: Wild Hunt")
+        );
+        assert_eq!(matcher.canonical("GOTY"), "goty");
+        assert_eq!(matcher.canonical("Bigoty"), "bigoty");
+        assert_eq!(base_title("Unremastered"), "unremastered");
+        assert_eq!(base_title("Bigoty GOTY"), "bigoty");
+    }
+
+    #[test]
+    fn applies_guild_aliases() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("csgo".to_string(), "counter strike 2".to_string());
+        aliases.insert("skyrim".to_string(), "the elder scrolls v skyrim".to_string());
+        let matcher = TitleMatcher::new(&aliases);
+        assert_eq!(matcher.canonical("CSGO"), "counter strike 2");
+        assert_eq!(matcher.canonical("Skyrim Special Edition"), "the elder scrolls v skyrim");
+        assert_eq!(matcher.canonical("Counter-Strike 2"), "counter strike 2");
+    }
+
+    #[test]
+    fn aliases_games_named_with_an_edition() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("csgo".to_string(), base_title("Counter-Strike 2 Deluxe Edition"));
+        aliases.insert("dos2".to_string(), "divinity original sin 2 definitive edition".to_string());
+        let matcher = TitleMatcher::new(&aliases);
+        assert_eq!(aliases["csgo"], "counter strike 2");
+        assert_eq!(matcher.canonical("CSGO"), matcher.canonical("Counter-Strike 2 Deluxe Edition"));
+        assert_eq!(matcher.canonical("DOS2"), matcher.canonical("Divinity: Original Sin 2 - Definitive Edition"));
+    }
+}
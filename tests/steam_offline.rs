@@ -0,0 +1,123 @@
+//! Suggestion and common games flows against steam served from a fixture,
+//! both in memory and through the local stub server.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use serenity::model::id::UserId;
+use serenity::prelude::*;
+
+use game_randomizer::endpoints::{GameSource, Source};
+use game_randomizer::endpoints::steam::{self, FakeSteam, Visibility, WebApi};
+use game_randomizer::players::{common_games, gather_library, Account};
+use game_randomizer::suggestions::{SuggestedGame, SuggestionBook};
+use game_randomizer::tags::steam_tags;
+use game_randomizer::titles::TitleMatcher;
+
+const FIXTURE: &str = include_str!("fixtures/steam.json");
+const ALICE: u64 = 76561198000000001;
+const BOB: u64 = 76561198000000002;
+const CAROL: u64 = 76561198000000003;
+
+fn fake() -> FakeSteam {
+    FakeSteam::from_json(FIXTURE).unwrap()
+}
+
+async fn fake_client() -> steam::Client {
+    let mut client = steam::Client::new(fake());
+    client.fill_app_list().await.unwrap();
+    client
+}
+
+async fn stub_client() -> steam::Client {
+    let addr = steam::stub::start(fake()).await.unwrap();
+    let mut client = steam::Client::new(WebApi::with_base_url("key", &format!{"http://{}", addr}));
+    client.fill_app_list().await.unwrap();
+    client
+}
+
+async fn suggest(client: &steam::Client) {
+    let mut book = SuggestionBook::default();
+    for query in ["620", "Valheim"] {
+        let game = client.lookup(query).await.unwrap();
+        let tags = match game.id.parse::<u32>() {
+            Ok(id) => client.app_details(id).await.map(|d| steam_tags(&d)).unwrap_or_default(),
+            Err(_) => Vec::new()
+        };
+        book.add(UserId(1), SuggestedGame::Store(game)).unwrap().tags.extend(tags);
+    }
+    assert!(client.lookup("Half-Life 3").await.is_err());
+    let again = client.lookup("Portal 2").await.unwrap();
+    assert!(book.add(UserId(2), SuggestedGame::Store(again)).is_err());
+
+    let portal = book.get(1).unwrap();
+    assert_eq!(portal.title(), "Portal 2");
+    assert_eq!(
+        portal.tags.iter().map(String::as_str).collect::<Vec<_>>(),
+        vec!["action", "adventure", "co-op", "singleplayer"]
+    );
+    assert!(book.get(2).unwrap().tags.is_empty());
+}
+
+async fn find_common(client: steam::Client) {
+    let client: Arc<Mutex<dyn GameSource>> = Arc::new(Mutex::new(client));
+    let mut sources = HashMap::new();
+    sources.insert(Source::Steam, client);
+    let aliases = BTreeMap::new();
+    let matcher = TitleMatcher::new(&aliases);
+    let players = [
+        (vec![Account::new("steam", &ALICE.to_string())], vec!["Deep Rock Galactic".to_string()]),
+        (vec![Account::new("steam", &BOB.to_string()), Account::new("origin", "bob")], Vec::new()),
+    ];
+    let mut libraries = Vec::new();
+    for (accounts, manual) in players.iter() {
+        let (library, failed) = gather_library(accounts, manual, &sources, &matcher).await;
+        assert!(failed.is_empty());
+        libraries.push(library);
+    }
+    let common: Vec<String> = common_games(libraries).iter().map(|g| g.to_string()).collect();
+    assert_eq!(common, vec![
+        "Deep Rock Galactic - https://store.steampowered.com/app/548430/",
+        "Portal 2 - https://store.steampowered.com/app/620/",
+        "Valheim - https://store.steampowered.com/app/892970/",
+    ]);
+
+    let private = [Account::new("steam", &CAROL.to_string())];
+    let (library, failed) = gather_library(&private, &[], &sources, &matcher).await;
+    assert!(library.is_empty() && failed.is_empty());
+}
+
+#[tokio::test]
+async fn suggests_from_fake() {
+    suggest(&fake_client().await).await;
+}
+
+#[tokio::test]
+async fn finds_common_games_from_fake() {
+    find_common(fake_client().await).await;
+}
+
+#[tokio::test]
+async fn suggests_through_stub() {
+    suggest(&stub_client().await).await;
+}
+
+#[tokio::test]
+async fn finds_common_games_through_stub() {
+    find_common(stub_client().await).await;
+}
+
+#[tokio::test]
+async fn stub_answers_like_steam() {
+    let client = stub_client().await;
+    assert_eq!(client.resolve_vanity_user("gaben").await.unwrap().to_string(), "76561197960287930");
+    assert!(client.resolve_vanity_user("nobody").await.is_err());
+    let ids = [ALICE.into(), CAROL.into(), 76561198000000099.into()];
+    let summaries = client.player_summaries(&ids).await.unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[0].name, "alice");
+    assert_eq!(summaries[0].visibility, Visibility::Public);
+    assert_eq!(summaries[1].visibility, Visibility::Private);
+    assert!(client.user_owned_games(&CAROL.into()).await.unwrap().is_empty());
+    assert_eq!(client.user_owned_games(&BOB.into()).await.unwrap().len(), 3);
+    assert!(client.app_details(892970).await.is_err());
+}